@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+use log::{error, info};
+use reqwest::StatusCode;
+use crate::config::AlertConfig;
+use crate::http;
+
+// 将运行错误推送到告警平台，min_interval 内只发送一次
+#[derive(Default)]
+pub struct Alerter {
+    last_sent_at: Option<Instant>,
+}
+
+impl Alerter {
+    pub async fn send(&mut self, config: &AlertConfig, err: &anyhow::Error) {
+        let Some(platform) = config.target.platform() else {
+            return;
+        };
+        if let Some(last) = self.last_sent_at {
+            if last.elapsed() < Duration::from_secs(config.min_interval) {
+                info!("alert suppressed by rate limit");
+                return;
+            }
+        }
+
+        let message = if is_auth_error(err) {
+            format!("github-trending: 平台凭证可能已失效，请检查配置\n\n{:#}", err)
+        } else {
+            format!("github-trending: 运行出错\n\n{:#}", err)
+        };
+        self.last_sent_at = Some(Instant::now());
+        if let Err(e) = platform.post(&message).await {
+            error!("send alert to {} failed: {:#}", platform.name(), e);
+        }
+    }
+}
+
+// 请求返回 401/403 时认为平台凭证失效
+fn is_auth_error(err: &anyhow::Error) -> bool {
+    matches!(
+        http::error_status(err),
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+    )
+}
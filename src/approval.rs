@@ -0,0 +1,20 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use crate::config::PreviewConfig;
+use crate::schedule::PendingPost;
+
+// 保存等待审核的内容，以项目 id 为 field
+pub async fn save(conn: &mut redis::aio::Connection, config: &PreviewConfig, id: &str, posts: &[PendingPost]) -> Result<()> {
+    conn.hset::<_, _, _, ()>(&config.key, id, serde_json::to_string(posts)?).await?;
+    Ok(())
+}
+
+// 取出并删除等待审核的内容
+pub async fn take(conn: &mut redis::aio::Connection, config: &PreviewConfig, id: &str) -> Result<Vec<PendingPost>> {
+    let value: Option<String> = conn.hget(&config.key, id).await?;
+    let Some(value) = value else {
+        return Ok(vec![]);
+    };
+    conn.hdel::<_, _, ()>(&config.key, id).await?;
+    Ok(serde_json::from_str(&value)?)
+}
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use redis::streams::StreamMaxlen;
+use redis::AsyncCommands;
+use crate::config::AuditConfig;
+use crate::repo::now_ts;
+
+pub async fn record(
+    conn: &mut redis::aio::Connection,
+    config: &AuditConfig,
+    id: &str,
+    platform: &str,
+    content: &str,
+    token_usage: u64,
+) -> Result<()> {
+    let items = [
+        ("repo", id.to_string()),
+        ("platform", platform.to_string()),
+        ("content", content.to_string()),
+        ("timestamp", now_ts().to_string()),
+        ("token_usage", token_usage.to_string()),
+    ];
+    conn.xadd_maxlen::<_, _, _, _, ()>(
+        &config.stream_key,
+        StreamMaxlen::Approx(config.max_len),
+        "*",
+        &items,
+    )
+    .await?;
+    Ok(())
+}
@@ -1,16 +1,130 @@
+use std::collections::HashMap;
+use std::env;
 use std::fs::File;
 use std::io::Read;
 use serde::Deserialize;
-use anyhow::Result;
+use chrono::{DateTime, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use anyhow::{bail, Context, Result};
+use crate::http;
+use crate::github::{Release, RepoMetadata};
 use crate::repo::Repo;
-use super::platform::zsxq;
+use crate::server::ServerConfig;
+use crate::store::StoreConfig;
+use super::platform::types::Platform;
+use super::platform::{bluesky, file, nats, telegram, zsxq};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub interval: IntervalConfig,
-    pub redis: RedisConfig,
+    #[serde(default)]
+    pub trending: TrendingConfig,
+    // store.type = "file" 且没有开启依赖 Redis 的功能时可以省略
+    pub redis: Option<RedisConfig>,
+    // 推送去重记录的存储方式，默认保存在 Redis 中
+    #[serde(default)]
+    pub store: StoreConfig,
+    #[serde(default)]
+    pub openai: OpenAIConfig,
     pub denylist: DenylistConfig,
+    #[serde(default)]
+    pub filter: FilterConfig,
     pub zsxq: Option<zsxq::Zsxq>,
+    pub bluesky: Option<bluesky::Bluesky>,
+    pub telegram: Option<telegram::Telegram>,
+    // 写入本地目录，用于归档和调试模板
+    pub file: Option<file::File>,
+    // 以 JSON 消息发布到 NATS，由下游服务投递
+    pub nats: Option<nats::Nats>,
+    pub audit: Option<AuditConfig>,
+    pub similarity: Option<SimilarityConfig>,
+    pub content_moderation: Option<ModerationConfig>,
+    // 定时推送，设置后项目先进入队列，在指定时间释放
+    pub schedule: Option<ScheduleConfig>,
+    // 只在每天的这段时间内推送，其余时间不获取也不标记，留到窗口打开后推送
+    pub active_hours: Option<ActiveHoursConfig>,
+    // 预览平台，设置后内容先发送到预览平台，审核通过后再推送
+    pub preview: Option<PreviewConfig>,
+    // 每周汇总推送
+    pub recap: Option<RecapConfig>,
+    #[serde(default)]
+    pub fast_path: FastPathConfig,
+    // 其他机器人的去重记录，命中任意一个时跳过该项目
+    #[serde(default)]
+    pub external_dedup: Vec<ExternalDedup>,
+    pub alert: Option<AlertConfig>,
+    // 启动和退出时发送到该平台，用于确认服务状态
+    pub heartbeat: Option<HeartbeatConfig>,
+    // 每轮结束后发送获取、推送和跳过的数量，供运维查看
+    pub cycle_report: Option<CycleReportConfig>,
+    // 是否翻译项目描述，用于模板中的 {translated_description}
+    #[serde(default)]
+    pub translate_description: bool,
+    // 是否翻译项目名称，用于模板中的 {translated_name}
+    #[serde(default)]
+    pub translate_name: bool,
+    #[serde(default)]
+    pub post_mode: PostMode,
+    // failover 模式下平台的尝试顺序，未设置时按配置文件中的平台顺序
+    pub failover_order: Option<Vec<String>>,
+    // 每轮结束后写入 JSON 格式的运行统计
+    pub metrics_file: Option<String>,
+    // 企业网络中 HTTPS 被代理解密时，信任代理的根证书
+    #[serde(default)]
+    pub tls: TlsConfig,
+    // 可选的 HTTP 服务，提供 POST /trigger 手动触发一轮推送
+    pub server: Option<ServerConfig>,
+    // 跳过与最近推送过的项目描述相同的项目，例如改名后的 fork
+    #[serde(default)]
+    pub dedup_description: bool,
+    // 描述哈希的保存时间（秒），未设置时使用 interval.post_ttl
+    pub dedup_description_ttl: Option<usize>,
+    // 追加在每条推送末尾的内容，平台可以单独覆盖
+    pub post_footer: Option<String>,
+    // 没有配置任何平台时的处理方式
+    #[serde(default)]
+    pub no_platforms: NoPlatformsAction,
+    // 首次运行（还没有任何推送记录）时的处理方式
+    #[serde(default)]
+    pub cold_start: ColdStart,
+    // cold_start = "limit_n" 时首次运行推送的项目数量，默认 5
+    pub cold_start_limit: Option<usize>,
+    // 每轮最多推送同一作者的项目数量，超出的项目不标记，留到之后的轮次
+    pub max_per_author: Option<usize>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColdStart {
+    // 推送全部项目
+    #[default]
+    PostAll,
+    // 只标记为已推送，之后新上榜的项目才会推送
+    SkipFirst,
+    // 只推送排名前 cold_start_limit 的项目，其余标记为已推送
+    LimitN,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoPlatformsAction {
+    // 拒绝启动
+    #[default]
+    Error,
+    // 只在日志中输出将要推送的项目
+    LogOnly,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PostMode {
+    // 推送到所有平台
+    #[default]
+    Broadcast,
+    // 按顺序尝试，第一个成功后停止
+    Failover,
 }
 
 #[derive(Deserialize)]
@@ -18,33 +132,969 @@ pub struct IntervalConfig {
     pub post_ttl: usize,
     pub fetch_interval: u64,
     pub post_interval: u64,
+    #[serde(default)]
+    pub dedup_window: DedupWindow,
+    // calendar_day 模式使用的时区，例如 Asia/Shanghai
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    // 超过该时间（秒）没有成功推送时重建 HTTP 客户端和 Redis 连接
+    pub watchdog: Option<u64>,
+    // 每轮最长运行时间（秒），超出后剩余项目留到下一轮
+    pub cycle_budget: Option<u64>,
+    // 获取和推送分别在两个任务中进行，推送较慢时不影响按 fetch_interval 获取
+    #[serde(default)]
+    pub pipeline: bool,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupWindow {
+    // 推送后 post_ttl 秒内不再推送
+    #[default]
+    Ttl,
+    // 每个自然日最多推送一次，本地零点后重置
+    CalendarDay,
+}
+
+impl IntervalConfig {
+    pub fn local_now(&self) -> DateTime<Tz> {
+        Utc::now().with_timezone(&self.timezone.parse().unwrap_or(Tz::UTC))
+    }
+
+    // 去重使用的 id，calendar_day 模式下加上本地日期
+    pub fn dedup_id(&self, id: &str) -> String {
+        match self.dedup_window {
+            DedupWindow::Ttl => id.to_string(),
+            DedupWindow::CalendarDay => format!("{}:{}", self.local_now().format("%Y-%m-%d"), id),
+        }
+    }
+
+    // 去重 key 的过期时间，calendar_day 模式下到本地零点过期
+    pub fn dedup_ttl(&self, post_ttl: Option<usize>) -> usize {
+        match self.dedup_window {
+            DedupWindow::Ttl => post_ttl.unwrap_or(self.post_ttl),
+            DedupWindow::CalendarDay => {
+                let now = self.local_now();
+                let seconds = now.num_seconds_from_midnight() as usize;
+                86400usize.saturating_sub(seconds).max(1)
+            }
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        if self.platforms().is_empty() && self.no_platforms == NoPlatformsAction::Error {
+            bail!("no platform configured, configure [zsxq], [bluesky], [telegram], [file] or [nats], or set no_platforms = \"log_only\"");
+        }
+        if self.interval.post_ttl == 0 {
+            bail!("interval.post_ttl must be greater than 0");
+        }
+        if self.interval.fetch_interval == 0 {
+            bail!("interval.fetch_interval must be greater than 0");
+        }
+        if self.interval.timezone.parse::<Tz>().is_err() {
+            bail!("interval.timezone is not a valid timezone: {}", self.interval.timezone);
+        }
+        match &self.redis {
+            Some(redis) if redis.url.urls().is_empty() => bail!("redis.url must not be empty"),
+            Some(_) => {}
+            None if !matches!(self.store, StoreConfig::File { .. }) => {
+                bail!("[redis] is required unless store.type = \"file\"");
+            }
+            None => {
+                if let Some(feature) = self.redis_features().first() {
+                    bail!("{} requires [redis]", feature);
+                }
+            }
+        }
+        if self.telegram.as_ref().is_some_and(|telegram| telegram.chats.is_empty()) {
+            bail!("telegram.chats must not be empty");
+        }
+        if self.trending.mode == TrendingMode::Search
+            && self.trending.search.as_ref().is_none_or(|search| search.query.trim().is_empty())
+        {
+            bail!("trending.mode = \"search\" requires trending.search.query");
+        }
+        if self.interval.pipeline && self.trending.mode == TrendingMode::Developers {
+            bail!("interval.pipeline does not support trending.mode = \"developers\"");
+        }
+        if let Some(order) = &self.failover_order {
+            let platforms = self.platforms();
+            if let Some(name) = order.iter().find(|name| !platforms.iter().any(|p| p.name() == *name)) {
+                bail!("failover_order contains unknown platform: {}", name);
+            }
+        }
+        if let Some(platform) = self
+            .platforms()
+            .into_iter()
+            .find(|p| p.options().reply_on_retrend.is_some() && !p.supports_reply())
+        {
+            bail!("{}.reply_on_retrend requires a platform that supports replies", platform.name());
+        }
+        if let Some(politeness) = &self.trending.politeness {
+            if politeness.min_delay > politeness.max_delay {
+                bail!("trending.politeness.min_delay must not be greater than max_delay");
+            }
+        }
+        if let Some(sampling) = &self.trending.sampling {
+            if !(0.0..=1.0).contains(&sampling.randomness) {
+                bail!("trending.sampling.randomness must be within 0 ~ 1");
+            }
+        }
+        if let (Some(min), Some(max)) = (self.filter.min_repo_age_days, self.filter.max_repo_age_days) {
+            if min > max {
+                bail!("filter.min_repo_age_days must not be greater than filter.max_repo_age_days");
+            }
+        }
+        if let Some(similarity) = &self.similarity {
+            if !(0.0..=1.0).contains(&similarity.threshold) || similarity.window == 0 {
+                bail!("similarity.threshold must be within 0 ~ 1 and similarity.window must be greater than 0");
+            }
+        }
+        if let Some(schedule) = &self.schedule {
+            if schedule.times.is_empty() || schedule.parsed_times().len() != schedule.times.len() {
+                bail!("schedule.times must be a non-empty list of HH:MM");
+            }
+        }
+        if self.max_per_author == Some(0) {
+            bail!("max_per_author must be greater than 0");
+        }
+        if self.server.as_ref().is_some_and(|server| server.token.is_empty()) {
+            bail!("server.token must not be empty");
+        }
+        if let Some(active_hours) = &self.active_hours {
+            if active_hours.parsed().is_none() {
+                bail!("active_hours.start and active_hours.end must be different HH:MM");
+            }
+            if active_hours.timezone.as_ref().is_some_and(|timezone| timezone.parse::<Tz>().is_err()) {
+                bail!("active_hours.timezone is not a valid timezone");
+            }
+        }
+        if self.external_dedup.iter().any(|dedup| dedup.key.is_some() == dedup.set.is_some()) {
+            bail!("each external_dedup requires exactly one of key and set");
+        }
+        if let Some(preview) = &self.preview {
+            if preview.target.platform().is_none() {
+                bail!("preview requires a platform, e.g. [preview.zsxq]");
+            }
+        }
+        if self.heartbeat.as_ref().is_some_and(|heartbeat| heartbeat.target.platform().is_none()) {
+            bail!("heartbeat requires a platform, e.g. [heartbeat.telegram]");
+        }
+        if self.cycle_report.as_ref().is_some_and(|cycle_report| cycle_report.target.platform().is_none()) {
+            bail!("cycle_report requires a platform, e.g. [cycle_report.telegram]");
+        }
+        if let Some(recap) = &self.recap {
+            if NaiveTime::parse_from_str(&recap.time, "%H:%M").is_err() {
+                bail!("recap.time must be HH:MM");
+            }
+            if recap.target.platform().is_none() {
+                bail!("recap requires a platform, e.g. [recap.zsxq]");
+            }
+        }
+        if let Some(alert) = &self.alert {
+            if alert.target.platform().is_none() {
+                bail!("alert requires a platform, e.g. [alert.zsxq]");
+            }
+        }
+        if let Some(audit) = &self.audit {
+            if audit.stream_key.is_empty() {
+                bail!("audit.stream_key must not be empty");
+            }
+        }
+        Ok(())
+    }
+
+    // 开启的依赖 Redis 的功能，没有配置 [redis] 时不能使用；本轮进度和 star 记录直接跳过
+    fn redis_features(&self) -> Vec<String> {
+        let trending = &self.trending;
+        let mut features: Vec<String> = [
+            (self.schedule.is_some(), "schedule"),
+            (self.preview.is_some(), "preview"),
+            (self.recap.is_some(), "recap"),
+            (self.similarity.is_some(), "similarity"),
+            (self.audit.is_some(), "audit"),
+            (self.content_moderation.is_some(), "content_moderation"),
+            (!self.external_dedup.is_empty(), "external_dedup"),
+            (self.dedup_description, "dedup_description"),
+            (self.translate_name, "translate_name"),
+            (self.filter.needs_info(), "filter.skip_archived, repo age and require_recent_release"),
+            (trending.issue_counts, "trending.issue_counts"),
+            (trending.topics, "trending.topics"),
+            (trending.author_info, "trending.author_info"),
+            (trending.updated, "trending.updated"),
+            (trending.releases, "trending.releases"),
+            (trending.why_trending, "trending.why_trending"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        for platform in self.platforms() {
+            let options = platform.options();
+            let enabled = [
+                (options.pin_top, "pin_top"),
+                (options.edit_on_update, "edit_on_update"),
+                (options.reply_on_retrend.is_some(), "reply_on_retrend"),
+                (options.delete_after.is_some(), "delete_after"),
+            ];
+            for (_, name) in enabled.iter().filter(|(enabled, _)| *enabled) {
+                features.push(format!("{}.{}", platform.name(), name));
+            }
+        }
+        features
+    }
+
+    pub fn platforms(&self) -> Vec<&dyn Platform> {
+        let mut platforms: Vec<&dyn Platform> = vec![];
+        if let Some(zsxq) = &self.zsxq {
+            platforms.push(zsxq);
+        }
+        if let Some(bluesky) = &self.bluesky {
+            platforms.push(bluesky);
+        }
+        if let Some(telegram) = &self.telegram {
+            platforms.push(telegram);
+        }
+        if let Some(file) = &self.file {
+            platforms.push(file);
+        }
+        if let Some(nats) = &self.nats {
+            platforms.push(nats);
+        }
+        platforms
+    }
+
+    fn platforms_mut(&mut self) -> Vec<&mut dyn Platform> {
+        let mut platforms: Vec<&mut dyn Platform> = vec![];
+        if let Some(zsxq) = &mut self.zsxq {
+            platforms.push(zsxq);
+        }
+        if let Some(bluesky) = &mut self.bluesky {
+            platforms.push(bluesky);
+        }
+        if let Some(telegram) = &mut self.telegram {
+            platforms.push(telegram);
+        }
+        if let Some(file) = &mut self.file {
+            platforms.push(file);
+        }
+        if let Some(nats) = &mut self.nats {
+            platforms.push(nats);
+        }
+        platforms
+    }
+
+    // 将全局配置作为各平台未设置时的默认值
+    fn apply_defaults(&mut self) {
+        let post_footer = self.post_footer.clone();
+        for platform in self.platforms_mut() {
+            let options = platform.options_mut();
+            if options.post_footer.is_none() {
+                options.post_footer = post_footer.clone();
+            }
+        }
+    }
+
+    pub fn failover_chain(&self) -> Vec<&dyn Platform> {
+        let platforms = self.platforms();
+        match &self.failover_order {
+            Some(order) => order
+                .iter()
+                .filter_map(|name| platforms.iter().find(|p| p.name() == name).copied())
+                .collect(),
+            None => platforms,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct TrendingConfig {
+    #[serde(default)]
+    pub mode: TrendingMode,
+    // mode = "search" 时的查询条件
+    pub search: Option<SearchConfig>,
+    // 需要获取的语言列表，未设置时使用环境变量 TRENDING_LANGUAGE（逗号分隔），默认 go
+    pub languages: Option<Vec<String>>,
+    // 同时获取的语言数量，默认 4
+    pub fetch_concurrency: Option<usize>,
+    // 轮流合并各语言的项目，未开启时按语言顺序依次推送
+    #[serde(default)]
+    pub interleave_languages: bool,
+    // 推送队列中各语言的先后顺序，未列出的语言排在最后
+    #[serde(default)]
+    pub language_priority: Vec<String>,
+    // 当天没有项目时，依次放宽到 weekly、monthly
+    #[serde(default)]
+    pub widen_on_empty: bool,
+    pub user_agent: Option<String>,
+    // 影响 GitHub 返回的页面语言，也会影响描述是否被本地化
+    pub accept_language: Option<String>,
+    // 按自然语言筛选，对应 trending 页面的 Spoken Language，例如 zh
+    pub spoken_language: Option<String>,
+    // 同一项目在不同语言、时间范围、自然语言下上榜时分别推送
+    #[serde(default)]
+    pub dedup_per_context: bool,
+    // 排名按过滤黑名单之后的顺序重新计算
+    #[serde(default)]
+    pub rank_after_filter: bool,
+    // 通过 HEAD 请求获取改名后的仓库地址，每个项目会多一次请求
+    #[serde(default)]
+    pub canonicalize_repos: bool,
+    // 被 GitHub 限流时最多等待的时间（秒），默认 300
+    pub max_rate_limit_wait: Option<u64>,
+    // 解析出的异常项目（author 或 name 为空）超过该比例时重新获取一次页面，默认 0.2
+    pub max_malformed_ratio: Option<f64>,
+    // 推送前通过 GitHub API 获取 open issue / PR 数量，用于 {open_issues} {open_prs}
+    #[serde(default)]
+    pub issue_counts: bool,
+    // 推送前通过 GitHub API 获取 topics，用于 {topics}
+    #[serde(default)]
+    pub topics: bool,
+    // {topics} 中最多显示的 topic 数量，默认 5
+    pub max_topics: Option<usize>,
+    // 推送前通过 GitHub API 获取作者类型和关注者数量，用于 {author_type} {author_followers}
+    #[serde(default)]
+    pub author_info: bool,
+    // 推送前通过 GitHub API 获取最新 release，用于 {latest_release} {release_date}
+    #[serde(default)]
+    pub releases: bool,
+    // 推送前通过 GitHub API 获取最近一次推送代码的时间，用于 {updated}
+    #[serde(default)]
+    pub updated: bool,
+    // 推送前请模型根据 star 增长、最近的提交和 release 生成一句上榜原因，用于 {why_trending}
+    #[serde(default)]
+    pub why_trending: bool,
+    // 覆盖默认的上榜原因提示词，项目信息会拼接在后面
+    pub why_trending_prompt: Option<String>,
+    // 首次部署时没有 star 历史，{stars_delta} 等于 stars_today，按该选项预先准备历史
+    #[serde(default)]
+    pub prime_star_history: PrimeStarHistory,
+    // prime_star_history = "refetch" 时两次获取的间隔（秒），默认 300
+    pub prime_delay: Option<u64>,
+    // GitHub API 项目信息的缓存时间（秒），默认 600
+    pub metadata_ttl: Option<usize>,
+    // 按 stars_today 加权随机排序，未设置时按 trending 顺序推送
+    pub sampling: Option<SamplingConfig>,
+    // 抓取页面前随机等待并轮换 User-Agent，未设置时不启用
+    pub politeness: Option<PolitenessConfig>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimeStarHistory {
+    // 没有历史的项目 {stars_delta} 使用 stars_today
+    #[default]
+    Off,
+    // 按 stars_today 估算一个 fetch_interval 内的增量，不增加请求，但只是估算
+    Seed,
+    // 所有项目都没有历史时等待 prime_delay 秒后重新获取一次，增量是真实的，但首轮推送会延后且增量较小
+    Refetch,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PolitenessConfig {
+    // 每次请求前随机等待 min_delay ~ max_delay 毫秒
+    #[serde(default)]
+    pub min_delay: u64,
+    #[serde(default)]
+    pub max_delay: u64,
+    // 随机选择一个 User-Agent，会覆盖 trending.user_agent
+    #[serde(default)]
+    pub user_agents: Vec<String>,
+}
+
+impl TrendingConfig {
+    pub fn metadata_ttl(&self) -> usize {
+        self.metadata_ttl.unwrap_or(600)
+    }
+
+    pub fn languages(&self) -> Vec<String> {
+        match &self.languages {
+            Some(languages) if !languages.is_empty() => languages.clone(),
+            _ => env::var("TRENDING_LANGUAGE")
+                .unwrap_or("go".to_string())
+                .split(',')
+                .map(|language| language.trim().to_string())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SamplingConfig {
+    // 0 表示完全按 stars_today 加权，1 表示完全随机
+    pub randomness: f64,
+    // 固定随机种子，便于复现
+    pub seed: Option<u64>,
+}
+
+// API 地址、Key 和模型仍通过环境变量配置
+#[derive(Deserialize, Clone)]
+pub struct OpenAIConfig {
+    // 部分网关需要的额外请求头，例如 OpenRouter 的 HTTP-Referer、X-Title
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub auth: OpenAIAuth,
+    // Azure OpenAI 需要的 api-version 查询参数
+    pub api_version: Option<String>,
+    // 模型输出开头的客套话，以这些内容开头的首行会被移除
+    #[serde(default = "default_preamble_patterns")]
+    pub preamble_patterns: Vec<String>,
+    // 多个 API Key 轮询使用，未设置时使用环境变量 OPENAI_API_KEY
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    // Key 返回 401 或 429 后的冷却时间（秒）
+    #[serde(default = "default_key_cooldown")]
+    pub key_cooldown: u64,
+    // 生成项目简介的提示词，项目内容会拼接在后面，平台可以单独覆盖
+    #[serde(default = "default_summary_prompt")]
+    pub summary_prompt: String,
+}
+
+fn default_summary_prompt() -> String {
+    "假设你是一名资深技术专家，精通各种开源项目，请基于以下开源项目内容写一段简介内容，用中文回答：".to_string()
+}
+
+fn default_key_cooldown() -> u64 {
+    600
+}
+
+fn default_preamble_patterns() -> Vec<String> {
+    ["Here's", "Here is", "Sure", "以下是", "好的"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+impl Default for OpenAIConfig {
+    fn default() -> Self {
+        OpenAIConfig {
+            extra_headers: HashMap::new(),
+            auth: OpenAIAuth::default(),
+            api_version: None,
+            preamble_patterns: default_preamble_patterns(),
+            api_keys: vec![],
+            key_cooldown: default_key_cooldown(),
+            summary_prompt: default_summary_prompt(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAIAuth {
+    // Authorization: Bearer <key>
+    #[default]
+    Bearer,
+    // api-key: <key>
+    Azure,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendingMode {
+    // github.com/trending
+    #[default]
+    Repositories,
+    // github.com/trending/developers
+    Developers,
+    // GitHub 搜索接口，查询条件见 trending.search
+    Search,
+}
+
+fn default_search_sort() -> String {
+    "stars".to_string()
+}
+
+fn default_search_order() -> String {
+    "desc".to_string()
+}
+
+fn default_search_per_page() -> usize {
+    25
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SearchConfig {
+    // GitHub 搜索语法，例如 topic:llm language:rust
+    pub query: String,
+    // stars、forks、help-wanted-issues 或 updated
+    #[serde(default = "default_search_sort")]
+    pub sort: String,
+    // desc 或 asc
+    #[serde(default = "default_search_order")]
+    pub order: String,
+    // 每次获取的项目数量，最多 100
+    #[serde(default = "default_search_per_page")]
+    pub per_page: usize,
 }
 
 #[derive(Deserialize)]
 pub struct RedisConfig {
-    pub url: String,
+    pub url: RedisUrl,
+}
+
+// 支持单个地址或地址列表，列表中第一个为主库，其余为只读从库
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum RedisUrl {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl RedisUrl {
+    pub fn urls(&self) -> Vec<String> {
+        match self {
+            RedisUrl::Single(url) => vec![url.clone()],
+            RedisUrl::Multiple(urls) => urls.clone(),
+        }
+    }
+}
+
+fn default_moderation_prompt() -> String {
+    "请判断以下开源项目是否涉及色情、赌博、诈骗、盗版或垃圾推广等不适合推荐的内容，只回答 safe 或 unsafe。".to_string()
+}
+
+fn default_moderation_keywords() -> Vec<String> {
+    ["nsfw", "porn", "casino", "gambling", "airdrop"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn default_moderation_key_prefix() -> String {
+    "moderation".to_string()
+}
+
+fn default_moderation_cache_ttl() -> usize {
+    30 * 24 * 3600
+}
+
+#[derive(Deserialize)]
+pub struct ModerationConfig {
+    // 分类提示词，模型需要回答 safe 或 unsafe
+    #[serde(default = "default_moderation_prompt")]
+    pub prompt: String,
+    // 名称或描述包含这些关键词时直接跳过，不请求模型
+    #[serde(default = "default_moderation_keywords")]
+    pub keywords: Vec<String>,
+    #[serde(default = "default_moderation_key_prefix")]
+    pub key_prefix: String,
+    // 判断结果的缓存时间（秒）
+    #[serde(default = "default_moderation_cache_ttl")]
+    pub cache_ttl: usize,
+}
+
+fn default_schedule_key() -> String {
+    "github-trending:schedule".to_string()
+}
+
+fn default_per_slot() -> usize {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct ScheduleConfig {
+    // 每天的推送时间，格式为 HH:MM，使用 interval.timezone 时区
+    pub times: Vec<String>,
+    // 每个推送时间释放的条数，相邻两条之间间隔 interval.post_interval 秒
+    #[serde(default = "default_per_slot")]
+    pub per_slot: usize,
+    #[serde(default = "default_schedule_key")]
+    pub key: String,
+}
+
+impl ScheduleConfig {
+    pub fn parsed_times(&self) -> Vec<NaiveTime> {
+        self.times
+            .iter()
+            .filter_map(|time| NaiveTime::parse_from_str(time, "%H:%M").ok())
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct TlsConfig {
+    // PEM 格式的根证书，可以包含多个证书，在系统证书之外额外信任
+    pub ca_cert_path: Option<String>,
+    // 不校验证书，仅用于测试
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ActiveHoursConfig {
+    // HH:MM，end 早于 start 时表示跨零点，例如 20:00 ~ 02:00
+    pub start: String,
+    pub end: String,
+    // 未设置时使用 interval.timezone
+    pub timezone: Option<String>,
+}
+
+impl ActiveHoursConfig {
+    fn parsed(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let start = NaiveTime::parse_from_str(&self.start, "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(&self.end, "%H:%M").ok()?;
+        (start != end).then_some((start, end))
+    }
+
+    // 包含 start，不包含 end
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self.parsed() {
+            Some((start, end)) if start < end => start <= time && time < end,
+            Some((start, end)) => time >= start || time < end,
+            None => true,
+        }
+    }
+
+    pub fn is_active(&self, interval: &IntervalConfig) -> bool {
+        let timezone = self.timezone.as_deref().unwrap_or(&interval.timezone);
+        let now = Utc::now().with_timezone(&timezone.parse().unwrap_or(Tz::UTC));
+        self.contains(now.time())
+    }
+}
+
+// key 和 set 二选一，支持 {id} {author} {name} 占位符
+#[derive(Deserialize)]
+pub struct ExternalDedup {
+    // key 存在即视为已推送，例如 "other-bot:{id}"
+    pub key: Option<String>,
+    // 集合中包含项目 id 即视为已推送
+    pub set: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditConfig {
+    pub stream_key: String,
+    pub max_len: usize,
+}
+
+// 用于告警等场景的单个平台配置，例如 [alert.zsxq]
+#[derive(Deserialize, Clone)]
+pub struct PlatformTarget {
+    pub zsxq: Option<zsxq::Zsxq>,
+    pub bluesky: Option<bluesky::Bluesky>,
+    pub telegram: Option<telegram::Telegram>,
+    pub file: Option<file::File>,
+    pub nats: Option<nats::Nats>,
+}
+
+impl PlatformTarget {
+    pub fn platform(&self) -> Option<&dyn Platform> {
+        if let Some(zsxq) = &self.zsxq {
+            return Some(zsxq);
+        }
+        if let Some(bluesky) = &self.bluesky {
+            return Some(bluesky);
+        }
+        if let Some(telegram) = &self.telegram {
+            return Some(telegram);
+        }
+        if let Some(file) = &self.file {
+            return Some(file);
+        }
+        if let Some(nats) = &self.nats {
+            return Some(nats);
+        }
+        None
+    }
+}
+
+fn default_alert_interval() -> u64 {
+    3600
+}
+
+#[derive(Deserialize)]
+pub struct AlertConfig {
+    // 两次告警的最小间隔（秒）
+    #[serde(default = "default_alert_interval")]
+    pub min_interval: u64,
+    #[serde(flatten)]
+    pub target: PlatformTarget,
+}
+
+fn default_approval_key() -> String {
+    "github-trending:approval".to_string()
+}
+
+// 启动和退出时发送的消息，例如 [heartbeat.telegram]
+#[derive(Deserialize, Clone)]
+pub struct HeartbeatConfig {
+    #[serde(flatten)]
+    pub target: PlatformTarget,
+}
+
+// 每轮推送的统计摘要，例如 [cycle_report.telegram]
+#[derive(Deserialize)]
+pub struct CycleReportConfig {
+    #[serde(flatten)]
+    pub target: PlatformTarget,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewConfig {
+    // 保存待审核内容的 Redis hash
+    #[serde(default = "default_approval_key")]
+    pub key: String,
+    #[serde(flatten)]
+    pub target: PlatformTarget,
+}
+
+fn default_recap_key() -> String {
+    "github-trending:recap".to_string()
+}
+
+fn default_recap_weekday() -> Weekday {
+    Weekday::Sun
+}
+
+fn default_recap_time() -> String {
+    "20:00".to_string()
+}
+
+fn default_recap_top() -> usize {
+    10
+}
+
+fn default_recap_title() -> String {
+    "本周 GitHub Trending Top".to_string()
+}
+
+#[derive(Deserialize)]
+pub struct RecapConfig {
+    // 每周推送的星期和时间，使用 interval.timezone 时区
+    #[serde(default = "default_recap_weekday")]
+    pub weekday: Weekday,
+    #[serde(default = "default_recap_time")]
+    pub time: String,
+    // 按一周内推送的项目在推送后的 star 增长取前 top 个项目
+    #[serde(default = "default_recap_top")]
+    pub top: usize,
+    #[serde(default = "default_recap_title")]
+    pub title: String,
+    #[serde(default = "default_recap_key")]
+    pub key: String,
+    #[serde(flatten)]
+    pub target: PlatformTarget,
+}
+
+fn default_similarity_key() -> String {
+    "github-trending:summaries".to_string()
+}
+
+#[derive(Deserialize)]
+pub struct SimilarityConfig {
+    // 相似度阈值，取值 0 ~ 1
+    pub threshold: f64,
+    // 与最近多少条推送内容比较
+    pub window: usize,
+    #[serde(default = "default_similarity_key")]
+    pub key: String,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct DenylistConfig {
+    #[serde(default)]
     pub names: Vec<String>,
+    #[serde(default)]
     pub authors: Vec<String>,
+    #[serde(default)]
     pub descriptions: Vec<String>,
+    // 按语言单独配置的黑名单，与全局黑名单合并生效
+    #[serde(default)]
+    pub languages: HashMap<String, LanguageDenylist>,
+    // 从外部 TOML/JSON 文件或 URL 读取黑名单，与上面的配置合并
+    pub from_file: Option<String>,
+    pub from_url: Option<String>,
+}
+
+// 外部黑名单的格式
+#[derive(Deserialize, Default)]
+struct ExternalDenylist {
+    #[serde(default)]
+    names: Vec<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    descriptions: Vec<String>,
+}
+
+impl ExternalDenylist {
+    // 依次尝试按 JSON、TOML 解析
+    fn parse(content: &str) -> Result<Self> {
+        serde_json::from_str(content).or_else(|_| Ok(toml::from_str(content)?))
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LanguageDenylist {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub descriptions: Vec<String>,
+}
+
+fn denied(names: &[String], authors: &[String], descriptions: &[String], repo: &Repo) -> bool {
+    names.contains(&repo.name)
+        || authors.contains(&repo.author)
+        || descriptions
+        .iter()
+        .any(|description| {
+            repo.description
+                .to_lowercase()
+                .contains(&description.to_lowercase())
+        })
 }
 
 impl DenylistConfig {
+    // 读取外部黑名单并合并
+    async fn load_external(&mut self) -> Result<()> {
+        let mut sources = vec![];
+        if let Some(path) = &self.from_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("While reading denylist file {}", path))?;
+            sources.push(ExternalDenylist::parse(&content).with_context(|| format!("While parsing {}", path))?);
+        }
+        if let Some(url) = &self.from_url {
+            let content = http::client()
+                .get(url)
+                .timeout(std::time::Duration::from_secs(30))
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .with_context(|| format!("While fetching denylist {}", url))?;
+            sources.push(ExternalDenylist::parse(&content).with_context(|| format!("While parsing {}", url))?);
+        }
+        for source in sources {
+            self.names.extend(source.names);
+            self.authors.extend(source.authors);
+            self.descriptions.extend(source.descriptions);
+        }
+        Ok(())
+    }
+
     pub fn contains(&self, repo: &Repo) -> bool {
-        self.names.contains(&repo.name)
-            || self.authors.contains(&repo.author)
+        denied(&self.names, &self.authors, &self.descriptions, repo)
             || self
-            .descriptions
+            .languages
+            .iter()
+            .filter(|(language, _)| language.eq_ignore_ascii_case(&repo.source_language))
+            .any(|(_, list)| denied(&list.names, &list.authors, &list.descriptions, repo))
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct FastPathConfig {
+    // 这些作者的项目直接使用描述（开启 translate_description 时使用翻译），不调用模型生成简介
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
+
+impl FastPathConfig {
+    pub fn contains(&self, repo: &Repo) -> bool {
+        self.authors.iter().any(|author| author.eq_ignore_ascii_case(&repo.author))
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct FilterConfig {
+    // stars_today / stars 的最小比例，过滤掉总 star 很多但当天增长很少的项目
+    pub min_star_velocity: Option<f64>,
+    // 跳过已归档或被禁用的项目，每个项目会多一次 GitHub API 请求
+    #[serde(default)]
+    pub skip_archived: bool,
+    // 按创建时间过滤项目（天），同样需要请求 GitHub API
+    pub min_repo_age_days: Option<i64>,
+    pub max_repo_age_days: Option<i64>,
+    // 只保留描述为这些语言的项目，ISO 639-3 代码（eng、cmn）或英文名称，为空时不过滤
+    #[serde(default)]
+    pub allowed_description_languages: Vec<String>,
+    // 只保留最近 N 天内发布过 release 的项目，没有 release 的项目也会被跳过
+    pub require_recent_release: Option<i64>,
+}
+
+// 描述过短时语言检测不可靠，不过滤
+const MIN_DETECT_LENGTH: usize = 20;
+
+impl FilterConfig {
+    // 检测不出语言或结果不可靠时保留项目
+    fn allows_description_language(&self, description: &str) -> bool {
+        if self.allowed_description_languages.is_empty() || description.chars().count() < MIN_DETECT_LENGTH {
+            return true;
+        }
+        let Some(info) = whatlang::detect(description).filter(|info| info.is_reliable()) else {
+            return true;
+        };
+        let lang = info.lang();
+        self.allowed_description_languages
             .iter()
-            .map(|description| {
-                repo.description
-                    .to_lowercase()
-                    .contains(&description.to_lowercase())
-            })
-            .any(|b| b)
+            .any(|allowed| allowed.eq_ignore_ascii_case(lang.code()) || allowed.eq_ignore_ascii_case(lang.eng_name()))
+    }
+
+    // 是否需要通过 GitHub API 获取项目信息或 release
+    pub fn needs_info(&self) -> bool {
+        self.needs_metadata() || self.require_recent_release.is_some()
+    }
+
+    pub fn needs_metadata(&self) -> bool {
+        self.skip_archived || self.min_repo_age_days.is_some() || self.max_repo_age_days.is_some()
+    }
+
+    // 解析不到发布时间时按没有 release 处理
+    pub fn rejects_release(&self, release: Option<&Release>) -> Option<String> {
+        let days = self.require_recent_release?;
+        let Some(published_at) = release
+            .and_then(|release| release.published_at.as_deref())
+            .and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok())
+        else {
+            return Some("no release".to_string());
+        };
+        let age = (OffsetDateTime::now_utc() - published_at).whole_days();
+        (age > days).then(|| format!("last release {} days ago", age))
+    }
+
+    // 根据 GitHub API 返回的项目信息判断是否跳过，返回跳过原因
+    pub fn rejects(&self, metadata: &RepoMetadata) -> Option<String> {
+        if self.skip_archived && (metadata.archived || metadata.disabled) {
+            return Some("archived or disabled".to_string());
+        }
+        // 解析不到创建时间时不过滤
+        let created_at = metadata
+            .created_at
+            .as_deref()
+            .and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok())?;
+        let age = (OffsetDateTime::now_utc() - created_at).whole_days();
+        if self.min_repo_age_days.is_some_and(|min| age < min) {
+            return Some(format!("too new ({} days)", age));
+        }
+        if self.max_repo_age_days.is_some_and(|max| age > max) {
+            return Some(format!("too old ({} days)", age));
+        }
+        None
+    }
+
+    pub fn allows(&self, repo: &Repo) -> bool {
+        if !self.allows_description_language(&repo.description) {
+            return false;
+        }
+        if let Some(min) = self.min_star_velocity {
+            // 解析不到总 star 数时不过滤
+            if repo.stars > 0 && (repo.stars_today as f64 / repo.stars as f64) < min {
+                return false;
+            }
+        }
+        true
     }
 }
 
@@ -52,5 +1102,83 @@ pub fn read_file(path: &str) -> Result<Config> {
     let mut file = File::open(path)?;
     let mut content = String::new();
     file.read_to_string(&mut content)?;
-    Ok(toml::from_str(&content)?)
+    let mut config: Config = toml::from_str(&content)?;
+    config.apply_defaults();
+    Ok(config)
+}
+
+// 读取配置文件以及其中引用的外部资源
+pub async fn load(path: &str) -> Result<Config> {
+    let mut config = read_file(path)?;
+    // 读取外部黑名单等请求也需要使用自定义证书
+    http::configure_tls(&config.tls).context("While configuring tls")?;
+    config.denylist.load_external().await.context("While loading external denylist")?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActiveHoursConfig, Config, FilterConfig};
+    use crate::github::Release;
+    use chrono::NaiveTime;
+
+    #[test]
+    fn test_require_recent_release() {
+        let filter = FilterConfig {
+            require_recent_release: Some(30),
+            ..Default::default()
+        };
+        let release = |published_at: &str| Release {
+            tag_name: "v1.0.0".to_string(),
+            published_at: Some(published_at.to_string()),
+        };
+        let recent = time::OffsetDateTime::now_utc() - time::Duration::days(3);
+        let recent = recent.format(&time::format_description::well_known::Rfc3339).unwrap();
+        assert_eq!(filter.rejects_release(Some(&release(&recent))), None);
+        assert!(filter.rejects_release(Some(&release("2020-01-01T00:00:00Z"))).is_some());
+        assert_eq!(filter.rejects_release(None), Some("no release".to_string()));
+        assert_eq!(FilterConfig::default().rejects_release(None), None);
+    }
+
+    #[test]
+    fn test_active_hours_across_midnight() {
+        let time = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        let day = ActiveHoursConfig { start: "08:00".to_string(), end: "22:00".to_string(), timezone: None };
+        assert!(day.contains(time("08:00")));
+        assert!(!day.contains(time("22:00")));
+        assert!(!day.contains(time("03:00")));
+
+        let night = ActiveHoursConfig { start: "20:00".to_string(), end: "02:00".to_string(), timezone: None };
+        assert!(night.contains(time("23:30")));
+        assert!(night.contains(time("01:59")));
+        assert!(!night.contains(time("12:00")));
+    }
+
+    #[test]
+    fn test_allowed_description_languages() {
+        let filter = FilterConfig {
+            allowed_description_languages: vec!["eng".to_string(), "Mandarin".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.allows_description_language("A fast and lightweight web framework written in Rust"));
+        assert!(filter.allows_description_language("一个使用 Rust 编写的快速轻量级网络框架，支持异步和中间件"));
+        assert!(!filter.allows_description_language("Ein schnelles und leichtgewichtiges Web-Framework, geschrieben in Rust"));
+        // 过短的描述不过滤
+        assert!(filter.allows_description_language("Schnell"));
+    }
+
+    #[test]
+    fn test_redis_optional_with_file_store() {
+        let parse = |content: &str| {
+            let base = "[interval]\npost_ttl = 604800\nfetch_interval = 60\npost_interval = 60\n[trending]\n[openai]\n[denylist]\nnames = []\nauthors = []\ndescriptions = []\n[file]\ndir = \"posts\"\n";
+            let mut config: Config = toml::from_str(&format!("{}{}", base, content)).unwrap();
+            config.apply_defaults();
+            config
+        };
+        let file = "[store]\ntype = \"file\"\npath = \"posted.txt\"\n";
+        assert!(parse(file).validate().is_ok());
+        assert!(parse(&format!("{}[recap]\n", file)).validate().is_err());
+        assert!(parse("").validate().is_err());
+        assert!(parse("[redis]\nurl = \"redis://127.0.0.1\"\n").validate().is_ok());
+    }
 }
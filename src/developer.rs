@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+use std::convert::TryInto;
+use anyhow::Result;
+use log::{error, info};
+use crate::config::TrendingConfig;
+use crate::repo::{fetch_page, trending_url};
+
+#[derive(Debug)]
+pub struct Developer {
+    pub username: String,
+    pub name: String,
+    // 开发者的热门项目
+    pub repo_name: String,
+    pub repo_description: String,
+}
+
+impl Developer {
+    pub fn get_url(&self) -> String {
+        format!("https://github.com/{}", self.username)
+    }
+
+    pub fn id(&self) -> String {
+        format!("developers/{}", self.username)
+    }
+}
+
+fn text(element: Option<scraper::ElementRef>) -> String {
+    element
+        .map(|e| e.text().collect::<String>().trim().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_developers(html: String) -> Result<Vec<Developer>> {
+    let html = scraper::Html::parse_document(&html);
+    let developers = html
+        .select(&".Box article.Box-row".try_into().unwrap())
+        .filter_map(|developer| {
+            let link = developer.select(&"h1.h3 a".try_into().unwrap()).next()?;
+            let username = link.value().attr("href")?.trim_matches('/').to_string();
+            let name = text(Some(link));
+            let repo_name = text(developer.select(&"h1.h4 a".try_into().unwrap()).next());
+            let repo_description = text(developer.select(&"article div.f6".try_into().unwrap()).next());
+
+            Some(Developer {
+                username,
+                name,
+                repo_name,
+                repo_description,
+            })
+        })
+        .collect();
+
+    Ok(developers)
+}
+
+// 按配置的语言依次获取，部分语言失败时只记录日志，同一开发者只保留第一次出现
+pub async fn fetch_developers(config: &TrendingConfig) -> Result<Vec<Developer>> {
+    let mut developers = vec![];
+    let mut last_error = None;
+    for language in config.languages() {
+        info!("fetching {} developers...", language);
+        let url = trending_url(config, "developers", &language, "daily");
+        match fetch_page(config, &url).await.and_then(parse_developers) {
+            Ok(list) => developers.push(list),
+            Err(e) => {
+                error!("fetch {} developers failed: {:#}", language, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    if let (true, Some(e)) = (developers.is_empty(), last_error) {
+        return Err(e);
+    }
+    let mut seen = HashSet::new();
+    Ok(developers
+        .into_iter()
+        .flatten()
+        .filter(|developer| seen.insert(developer.username.clone()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_developers;
+
+    #[test]
+    fn test_parse_developers() {
+        let html = std::fs::read_to_string("testdata/developers.html").unwrap();
+        let developers = parse_developers(html).unwrap();
+        assert_eq!(developers.len(), 2);
+        assert_eq!(developers[0].username, "dtolnay");
+        assert_eq!(developers[0].name, "David Tolnay");
+        assert_eq!(developers[0].repo_name, "cxx");
+        assert_eq!(developers[0].repo_description, "Safe interop between Rust and C++");
+        assert_eq!(developers[1].username, "BurntSushi");
+        assert_eq!(developers[1].repo_description, "");
+        assert_eq!(developers[1].id(), "developers/BurntSushi");
+    }
+}
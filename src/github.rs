@@ -0,0 +1,110 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::http;
+
+// 被限流时最多等待的时间，启动和重新加载配置时更新
+static MAX_RATE_LIMIT_WAIT: AtomicU64 = AtomicU64::new(http::DEFAULT_MAX_RATE_LIMIT_WAIT);
+
+pub fn configure(max_rate_limit_wait: Option<u64>) {
+    MAX_RATE_LIMIT_WAIT.store(
+        max_rate_limit_wait.unwrap_or(http::DEFAULT_MAX_RATE_LIMIT_WAIT),
+        Ordering::Relaxed,
+    );
+}
+
+// GitHub REST API，设置 GITHUB_TOKEN 后可以提高请求频率限制
+pub async fn get(path: &str) -> Result<Value> {
+    let mut req = http::client()
+        .get(format!("https://api.github.com{}", path))
+        .header(reqwest::header::USER_AGENT, "github-trending")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Ok(token) = env::var("GITHUB_TOKEN") {
+        req = req.bearer_auth(token);
+    }
+    let max_wait = MAX_RATE_LIMIT_WAIT.load(Ordering::Relaxed);
+    Ok(http::send_github(req, max_wait).await?.json().await?)
+}
+
+// /repos/{owner}/{repo} 返回的项目信息中用到的字段
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RepoMetadata {
+    pub description: Option<String>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub stargazers_count: u64,
+    // 同时包含 issue 和 PR
+    #[serde(default)]
+    pub open_issues_count: u64,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub license: Option<License>,
+    pub created_at: Option<String>,
+    pub pushed_at: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct License {
+    pub spdx_id: Option<String>,
+    pub name: Option<String>,
+}
+
+// /users/{username} 返回的用户信息中用到的字段
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UserMetadata {
+    // User 或 Organization
+    #[serde(rename = "type")]
+    pub user_type: String,
+    #[serde(default)]
+    pub followers: u64,
+}
+
+pub async fn user_metadata(username: &str) -> Result<UserMetadata> {
+    let info = get(&format!("/users/{}", username)).await?;
+    Ok(serde_json::from_value(info)?)
+}
+
+// 最近的提交，只取提交信息的第一行和提交时间
+pub async fn recent_commits(author: &str, name: &str, count: usize) -> Result<Vec<String>> {
+    let commits = get(&format!("/repos/{}/{}/commits?per_page={}", author, name, count)).await?;
+    Ok(commits
+        .as_array()
+        .map(|commits| {
+            commits
+                .iter()
+                .filter_map(|commit| {
+                    let message = commit["commit"]["message"].as_str()?.lines().next()?;
+                    let date = commit["commit"]["committer"]["date"].as_str().unwrap_or_default();
+                    Some(format!("{} {}", date, message))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    // RFC 3339 格式
+    pub published_at: Option<String>,
+}
+
+// 最新的 release（包括预发布版本），没有 release 时返回 None
+pub async fn latest_release(author: &str, name: &str) -> Result<Option<Release>> {
+    let releases = get(&format!("/repos/{}/{}/releases?per_page=1", author, name)).await?;
+    let Some(release) = releases.as_array().and_then(|releases| releases.first()) else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_value(release.clone())?))
+}
+
+pub async fn repo_metadata(author: &str, name: &str) -> Result<RepoMetadata> {
+    let info = get(&format!("/repos/{}/{}", author, name)).await?;
+    Ok(serde_json::from_value(info)?)
+}
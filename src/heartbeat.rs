@@ -0,0 +1,83 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{Context, Result};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+use crate::config::{Config, HeartbeatConfig, TrendingMode};
+
+// 收到退出信号后设置，推送流程在两个项目之间检查，完成当前的推送后退出
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN_NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+pub fn is_shutting_down() -> bool {
+    SHUTDOWN.load(Ordering::Relaxed)
+}
+
+// 等待退出信号，用于打断轮次之间和推送间隔的等待
+pub async fn shutdown_requested() {
+    while !is_shutting_down() {
+        SHUTDOWN_NOTIFY.notified().await;
+    }
+}
+
+// 启动时发送，包含版本和主要配置，方便确认部署的是哪个版本
+pub async fn started(config: &Config, heartbeat: &HeartbeatConfig) {
+    send(heartbeat, &format!("github-trending 已启动（{}）", summary(config))).await;
+}
+
+// 收到 SIGTERM 或 SIGINT 时设置退出标记，当前的推送完成后由主循环发送下线消息并退出
+// 只在配置了 heartbeat 时接管这两个信号，再次收到信号时立即退出
+pub fn spawn_shutdown() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate()).context("While listening SIGTERM")?;
+    let mut interrupt = signal(SignalKind::interrupt()).context("While listening SIGINT")?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = interrupt.recv() => {}
+            }
+            if is_shutting_down() {
+                warn!("received another signal, exiting now");
+                std::process::exit(1);
+            }
+            info!("shutting down after the current post");
+            SHUTDOWN.store(true, Ordering::Relaxed);
+            SHUTDOWN_NOTIFY.notify_waiters();
+            SHUTDOWN_NOTIFY.notify_one();
+        }
+    });
+    Ok(())
+}
+
+pub async fn stopped(heartbeat: &HeartbeatConfig) {
+    send(heartbeat, &format!("github-trending 正在退出（v{}）", env!("CARGO_PKG_VERSION"))).await;
+}
+
+async fn send(heartbeat: &HeartbeatConfig, message: &str) {
+    let Some(platform) = heartbeat.target.platform() else {
+        return;
+    };
+    if let Err(e) = platform.post(message).await {
+        error!("send heartbeat to {} failed: {:#}", platform.name(), e);
+    }
+}
+
+fn summary(config: &Config) -> String {
+    let platforms: Vec<&str> = config.platforms().iter().map(|p| p.name()).collect();
+    let source = match config.trending.mode {
+        TrendingMode::Repositories => format!("languages: {}", config.trending.languages().join(",")),
+        TrendingMode::Developers => format!("developers, languages: {}", config.trending.languages().join(",")),
+        TrendingMode::Search => format!(
+            "search: {}",
+            config.trending.search.as_ref().map_or("", |search| search.query.as_str())
+        ),
+    };
+    format!(
+        "v{}，{}，platforms: {}，fetch_interval: {}s",
+        env!("CARGO_PKG_VERSION"),
+        source,
+        if platforms.is_empty() { "none".to_string() } else { platforms.join(",") },
+        config.interval.fetch_interval
+    )
+}
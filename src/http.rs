@@ -0,0 +1,132 @@
+use std::fs;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use anyhow::{anyhow, Context, Result};
+use log::{error, warn};
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, StatusCode};
+use crate::config::TlsConfig;
+
+// 限流时最多等待的时间（秒）和重试次数
+pub const DEFAULT_MAX_RATE_LIMIT_WAIT: u64 = 300;
+const RATE_LIMIT_RETRIES: usize = 2;
+
+struct TlsOptions {
+    ca_certs: Vec<Certificate>,
+    accept_invalid_certs: bool,
+}
+
+// 配置文件中的 [tls]，启动和重新加载配置时更新
+static TLS: Lazy<RwLock<TlsOptions>> = Lazy::new(|| {
+    RwLock::new(TlsOptions {
+        ca_certs: vec![],
+        accept_invalid_certs: false,
+    })
+});
+
+// 读取自定义根证书，证书无效时返回错误并保留之前的设置
+pub fn configure_tls(config: &TlsConfig) -> Result<()> {
+    let ca_certs = match &config.ca_cert_path {
+        Some(path) => {
+            let pem = fs::read(path).context(format!("While reading {}", path))?;
+            Certificate::from_pem_bundle(&pem).context(format!("While parsing certificates in {}", path))?
+        }
+        None => vec![],
+    };
+    let options = TlsOptions {
+        ca_certs,
+        accept_invalid_certs: config.danger_accept_invalid_certs,
+    };
+    let client = build_client(&options).context("While building http client")?;
+    if options.accept_invalid_certs {
+        warn!("tls.danger_accept_invalid_certs is enabled, certificates are not verified");
+    }
+    *TLS.write().unwrap() = options;
+    *CLIENT.write().unwrap() = client;
+    Ok(())
+}
+
+fn build_client(options: &TlsOptions) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().danger_accept_invalid_certs(options.accept_invalid_certs);
+    for cert in options.ca_certs.iter() {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    builder.build()
+}
+
+fn build_or_default(options: &TlsOptions) -> reqwest::Client {
+    build_client(options).unwrap_or_else(|e| {
+        error!("build http client failed, falling back to default: {:#}", e);
+        reqwest::Client::new()
+    })
+}
+
+// 所有请求共用一个客户端和连接池，自定义根证书在系统证书之外额外信任
+static CLIENT: Lazy<RwLock<reqwest::Client>> = Lazy::new(|| RwLock::new(build_or_default(&TLS.read().unwrap())));
+
+// 返回共享客户端的克隆，克隆之间共用连接池
+pub fn client() -> reqwest::Client {
+    CLIENT.read().unwrap().clone()
+}
+
+// 丢弃连接池中可能已经失效的连接
+pub fn reset_client() {
+    let client = build_or_default(&TLS.read().unwrap());
+    *CLIENT.write().unwrap() = client;
+}
+
+// 从错误链中取出 HTTP 响应状态码
+pub fn error_status(err: &anyhow::Error) -> Option<StatusCode> {
+    err.chain().find_map(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .and_then(|e| e.status())
+            .or_else(|| match cause.downcast_ref::<reqwest_middleware::Error>() {
+                Some(reqwest_middleware::Error::Reqwest(e)) => e.status(),
+                _ => None,
+            })
+    })
+}
+
+// GitHub 限流时需要等待的时间，优先使用 Retry-After，其次 X-RateLimit-Reset
+fn rate_limit_wait(resp: &reqwest::Response) -> Option<Duration> {
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    let limited = resp.status() == StatusCode::TOO_MANY_REQUESTS
+        || (resp.status() == StatusCode::FORBIDDEN
+            && (header("x-ratelimit-remaining") == Some(0) || header("retry-after").is_some()));
+    if !limited {
+        return None;
+    }
+    if let Some(secs) = header("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let wait = header("x-ratelimit-reset").map_or(60, |reset| reset.saturating_sub(now));
+    Some(Duration::from_secs(wait.max(1)))
+}
+
+// 发送请求，遇到 GitHub 限流时等待到重置时间（不超过 max_wait 秒）后重试
+pub async fn send_github(req: reqwest::RequestBuilder, max_wait: u64) -> Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let resp = req
+            .try_clone()
+            .ok_or_else(|| anyhow!("request can not be retried"))?
+            .send()
+            .await?;
+        match rate_limit_wait(&resp) {
+            Some(wait) if attempt < RATE_LIMIT_RETRIES => {
+                let wait = wait.min(Duration::from_secs(max_wait));
+                warn!("rate limited by GitHub ({}) on {}, waiting {}s", resp.status(), resp.url(), wait.as_secs());
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            _ => return Ok(resp.error_for_status()?),
+        }
+    }
+}
@@ -1,78 +1,1509 @@
-use anyhow::{Context, Result};
-use log::{error, info};
-use platform::types::Platform;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Context, Result};
+use log::{error, info, warn, LevelFilter};
+use platform::types::{Platform, Unsupported};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::sync::mpsc::{self, error::TrySendError};
 
+mod alert;
+mod approval;
+mod audit;
 mod config;
+mod developer;
+mod github;
+mod heartbeat;
+mod http;
+mod metrics;
+mod moderation;
 mod platform;
+mod pool;
+mod recap;
 mod repo;
+mod schedule;
+mod similarity;
+mod server;
+mod stats;
+mod store;
 mod openai;
 
-async fn main_loop(config: &config::Config, redis_conn: &mut redis::aio::Connection) -> Result<()> {
-    let repos = repo::fetch_repos().await.context("While fetching repo")?;
+// 跨轮次保存的运行状态
+struct State {
+    // 没有配置 [redis] 时为 None
+    redis_pool: Option<pool::RedisPool>,
+    last_posted_at: HashMap<String, Instant>,
+    // 本轮已经置顶过帖子的平台
+    pinned: HashSet<String>,
+    // 最近一次成功推送的时间，启动时为启动时间
+    last_success: Instant,
+    // store.type = "file" 时使用本地文件去重
+    file_store: Option<store::FileStore>,
+    // 本轮的获取和推送数量，用于 /trigger 的返回结果
+    cycle: server::CycleReport,
+}
+
+impl State {
+    // validate 保证依赖 Redis 的功能只在配置了 [redis] 时开启
+    fn redis(&mut self) -> Result<&mut pool::RedisPool> {
+        self.redis_pool.as_mut().ok_or_else(|| anyhow!("[redis] is not configured"))
+    }
+}
+
+async fn main_loop(config: &config::Config, state: &mut State) -> Result<()> {
+    let started_at = Instant::now();
+    prepare_cycle(config, state).await?;
+    if !is_active(config) {
+        info!("outside active hours, defer posting to the next cycle");
+        return Ok(());
+    }
+    if config.trending.mode == config::TrendingMode::Developers {
+        return developers_loop(config, state).await;
+    }
+
+    let repos = repo::fetch_repos(&config.trending).await.context("While fetching repo")?;
+    metrics::inc_fetches();
     info!("fetched {} repos", repos.len());
-    
-    for repo in repos {
-        if config.denylist.contains(&repo)
-            || repo::is_repo_posted(redis_conn, &repo)
+
+    post_repos(config, state, repos, started_at, false).await
+}
+
+fn is_active(config: &config::Config) -> bool {
+    config
+        .active_hours
+        .as_ref()
+        .is_none_or(|active_hours| active_hours.is_active(&config.interval))
+}
+
+// 每轮推送前释放定时队列、清理过期消息
+async fn prepare_cycle(config: &config::Config, state: &mut State) -> Result<()> {
+    state.pinned.clear();
+    state.cycle = server::CycleReport::default();
+    if let Some(schedule) = &config.schedule {
+        release_scheduled(config, state, schedule).await?;
+    }
+    delete_stale(config, state).await;
+    Ok(())
+}
+
+// pipeline 模式的获取任务，推送任务还在处理上一份列表时丢弃本次结果
+async fn produce(trending: config::TrendingConfig, fetch_interval: u64, tx: mpsc::Sender<Vec<repo::Repo>>) {
+    loop {
+        match repo::fetch_repos(&trending).await {
+            Ok(repos) => {
+                metrics::inc_fetches();
+                info!("fetched {} repos", repos.len());
+                match tx.try_send(repos) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => info!("still posting the previous list, skip this fetch"),
+                    Err(TrySendError::Closed(_)) => return,
+                }
+            }
+            Err(e) => {
+                error!("While fetching repo: {:#}", e);
+                metrics::inc_errors();
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(fetch_interval)).await;
+    }
+}
+
+// pipeline 模式：获取在单独的任务中按 fetch_interval 进行，当前任务负责推送
+async fn run_pipeline(
+    config: &mut config::Config,
+    state: &mut State,
+    config_file_path: &str,
+    alerter: &mut alert::Alerter,
+    hangup: &mut Signal,
+    triggers: &mut mpsc::Receiver<server::Trigger>,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::channel(1);
+    let spawn_producer = |config: &config::Config| {
+        tokio::spawn(produce(config.trending.clone(), config.interval.fetch_interval, tx.clone()))
+    };
+    let mut producer = spawn_producer(config);
+    loop {
+        tokio::select! {
+            repos = rx.recv() => {
+                let repos = repos.ok_or_else(|| anyhow!("fetch task stopped"))?;
+                let started_at = Instant::now();
+                let mut res = prepare_cycle(config, state).await;
+                if res.is_ok() {
+                    res = post_repos(config, state, repos, started_at, false).await;
+                }
+                finish_cycle(config, state, alerter, res).await;
+                if heartbeat::is_shutting_down() {
+                    producer.abort();
+                    return Ok(());
+                }
+            }
+            Some(reply) = triggers.recv() => {
+                info!("cycle triggered over http");
+                let res = main_loop(config, state).await;
+                let error = finish_cycle(config, state, alerter, res).await;
+                let _ = reply.send(state.cycle.finish(error));
+            }
+            _ = heartbeat::shutdown_requested() => {
+                producer.abort();
+                return Ok(());
+            }
+            _ = hangup.recv() => {
+                reload_config(config_file_path, config).await;
+                // 使用新的配置重新启动获取任务
+                producer.abort();
+                producer = spawn_producer(config);
+            }
+        }
+    }
+}
+
+// 记录本轮的错误并发送告警，写入运行统计，返回错误信息供 /trigger 使用
+async fn finish_cycle(
+    config: &config::Config,
+    state: &mut State,
+    alerter: &mut alert::Alerter,
+    res: Result<()>,
+) -> Option<String> {
+    let error = match res {
+        Ok(()) => None,
+        Err(e) => {
+            error!("{:#}", e);
+            metrics::inc_errors();
+            if let Some(alert) = &config.alert {
+                alerter.send(alert, &e).await;
+            }
+            Some(format!("{:#}", e))
+        }
+    };
+    if let Some(cycle_report) = &config.cycle_report {
+        send_cycle_report(cycle_report, &state.cycle.finish(error.clone())).await;
+    }
+    write_metrics(config);
+    check_watchdog(config, state).await;
+    error
+}
+
+// 不在活跃时段等没有获取项目且没有出错的轮次不发送
+async fn send_cycle_report(cycle_report: &config::CycleReportConfig, report: &server::CycleReport) {
+    if report.fetched == 0 && report.error.is_none() {
+        return;
+    }
+    let Some(platform) = cycle_report.target.platform() else {
+        return;
+    };
+    if let Err(e) = platform.post(&report.digest()).await {
+        error!("send cycle report to {} failed: {:#}", platform.name(), e);
+    }
+}
+
+// 记录 star 数并计算 {stars_delta}，首次部署时按 trending.prime_star_history 准备历史
+async fn track_stars(config: &config::Config, state: &mut State, repos: &mut [repo::Repo]) -> Result<()> {
+    let prime = config.trending.prime_star_history;
+    let seed_interval = (prime == config::PrimeStarHistory::Seed).then_some(config.interval.fetch_interval);
+    let tracked = repo::track_stars(state.redis()?.writer(), repos, seed_interval)
+        .await
+        .context("While tracking stars")?;
+    if prime != config::PrimeStarHistory::Refetch || tracked > 0 || repos.is_empty() {
+        return Ok(());
+    }
+
+    let delay = config.trending.prime_delay.unwrap_or(300);
+    info!("no star history yet, fetching again in {}s to prime it", delay);
+    tokio::time::sleep(Duration::from_secs(delay)).await;
+    let latest: HashMap<String, u64> = repo::fetch_repos(&config.trending)
+        .await
+        .context("While fetching repos to prime star history")?
+        .into_iter()
+        .map(|repo| (repo.id(), repo.stars))
+        .collect();
+    // 第二次没有获取到的项目保留 stars_today 作为增量
+    let mut refreshed: Vec<repo::Repo> = repos
+        .iter()
+        .filter_map(|repo| {
+            let stars = *latest.get(&repo.id())?;
+            Some(repo::Repo {
+                author: repo.author.clone(),
+                name: repo.name.clone(),
+                stars,
+                ..Default::default()
+            })
+        })
+        .collect();
+    repo::track_stars(state.redis()?.writer(), &mut refreshed, None)
+        .await
+        .context("While tracking stars")?;
+    for repo in repos.iter_mut() {
+        if let Some(fresh) = refreshed.iter().find(|fresh| fresh.id() == repo.id()) {
+            repo.stars = fresh.stars;
+            repo.stars_delta = fresh.stars_delta;
+        }
+    }
+    Ok(())
+}
+
+// 所有平台都没有推送记录时视为首次运行
+async fn is_cold_start(config: &config::Config, state: &mut State) -> Result<bool> {
+    if let Some(file_store) = &state.file_store {
+        return Ok(file_store.is_empty());
+    }
+    for platform in config.platforms() {
+        if repo::has_posts(state.redis()?.writer(), platform.name())
+            .await
+            .context("While checking cold start")?
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// 推送某一天 trending 的近似结果，用于给新频道补充历史内容
+async fn replay_day(config: &config::Config, state: &mut State, date: &str) -> Result<()> {
+    let date = time::Date::parse(date, time::macros::format_description!("[year]-[month]-[day]"))
+        .context(format!("invalid date {}, expected YYYY-MM-DD", date))?;
+    let repos = repo::fetch_replay(&config.trending, date).await.context("While fetching replay repos")?;
+    info!("replaying {} repos on {}", repos.len(), date);
+    post_repos(config, state, repos, Instant::now(), true).await
+}
+
+// 过滤、排序后逐个推送，replay 子命令也使用同样的流程
+// replay 的是历史数据，不记录 star、周报和进度，避免影响当天的推送
+async fn post_repos(
+    config: &config::Config,
+    state: &mut State,
+    repos: Vec<repo::Repo>,
+    started_at: Instant,
+    replay: bool,
+) -> Result<()> {
+    state.cycle.fetched = repos.len();
+    let mut repos: Vec<_> = repos.into_iter().filter(|repo| !config.denylist.contains(repo)).collect();
+    state.cycle.denied = state.cycle.fetched - repos.len();
+    let allowed = repos.len();
+    repos.retain(|repo| config.filter.allows(repo));
+    if config.filter.needs_info() {
+        repos = repo::filter_by_info(repos, &config.filter, state.redis()?.writer(), config.trending.metadata_ttl()).await;
+    }
+    state.cycle.filtered = allowed - repos.len();
+    if config.trending.rank_after_filter {
+        for (i, repo) in repos.iter_mut().enumerate() {
+            repo.rank = i + 1;
+        }
+    }
+    for repo in repos.iter_mut() {
+        repo.fast_path = config.fast_path.contains(repo);
+    }
+    if config.cold_start != config::ColdStart::PostAll && is_cold_start(config, state).await? {
+        let keep = match config.cold_start {
+            config::ColdStart::LimitN => config.cold_start_limit.unwrap_or(5).min(repos.len()),
+            _ => 0,
+        };
+        info!("cold start, posting {} of {} repos and marking the rest posted", keep, repos.len());
+        for repo in repos.drain(keep..) {
+            for platform in config.platforms() {
+                mark_posted(config, state, platform, &repo.post_id(), &repo.language, None, Some(repo.stars)).await?;
+            }
+        }
+    }
+
+    // 没有 Redis 时不记录 star 变化
+    if !replay && state.redis_pool.is_some() {
+        if let Err(e) = track_stars(config, state, &mut repos).await {
+            error!("{:#}", e);
+        }
+        if let Some(recap) = &config.recap {
+            if let Err(e) = send_recap(config, state, recap, &repos).await {
+                error!("{:#}", e);
+            }
+        }
+    }
+
+    if config.translate_description && openai::is_configured() {
+        if let Err(e) = repo::translate_descriptions(&mut repos).await {
+            error!("{:#}", e);
+        }
+    }
+
+    // 进度 key 需要在随机排序之前计算，保证同一份列表对应同一个 key
+    let progress_key = (!replay && state.redis_pool.is_some()).then(|| repo::progress_key(&repos));
+    if let Some(sampling) = &config.trending.sampling {
+        repo::weighted_shuffle(&mut repos, sampling);
+    }
+    let processed = match &progress_key {
+        Some(key) => repo::processed_ids(state.redis()?.writer(), key)
+            .await
+            .context("While reading cycle progress")?,
+        None => HashSet::new(),
+    };
+    if !processed.is_empty() {
+        info!("resuming cycle, {} repos already processed", processed.len());
+    }
+
+    let total = repos.len();
+    let mut posted_per_author: HashMap<String, usize> = HashMap::new();
+    for (i, mut repo) in repos.into_iter().enumerate() {
+        if processed.contains(&repo.post_id()) {
+            continue;
+        }
+        // 超出的项目不标记为已处理，之后的轮次还可以推送
+        if let Some(max) = config.max_per_author {
+            if posted_per_author.get(&repo.author.to_lowercase()).is_some_and(|count| *count >= max) {
+                info!("skip {}, already posted {} repos of {} in this cycle", repo.id(), max, repo.author);
+                continue;
+            }
+        }
+        // 超出本轮时间预算时停止，剩余项目不标记，下一轮继续处理
+        if let Some(budget) = config.interval.cycle_budget {
+            if started_at.elapsed() > Duration::from_secs(budget) {
+                warn!("cycle budget of {}s exceeded, leaving {} repos for the next cycle", budget, total - i);
+                break;
+            }
+        }
+        // 收到退出信号时停止，剩余项目没有标记，重启后继续推送
+        if heartbeat::is_shutting_down() {
+            info!("shutting down, leaving {} repos for the next run", total - i);
+            break;
+        }
+        // 推送过程中离开活跃时段时停止，剩余项目没有标记，窗口打开后继续推送
+        if !is_active(config) {
+            info!("active hours ended, leaving {} repos for the next window", total - i);
+            break;
+        }
+
+        if !config.external_dedup.is_empty()
+            && repo::is_posted_externally(state.redis()?, &config.external_dedup, &repo)
+                .await
+                .context("While checking external dedup")?
+        {
+            info!("skip repo {} posted by other bots", repo.id());
+            state.cycle.deduped += 1;
+            mark_processed(config, state, progress_key.as_deref(), &repo).await?;
+            continue;
+        }
+
+        if config.dedup_description
+            && repo::is_description_posted(state.redis()?, &repo)
+                .await
+                .context("While checking description posted")?
+        {
+            info!("skip repo {} with a recently posted description", repo.id());
+            state.cycle.deduped += 1;
+            mark_processed(config, state, progress_key.as_deref(), &repo).await?;
+            continue;
+        }
+
+        if let Some(moderation) = &config.content_moderation {
+            match moderation::is_safe(state.redis()?.writer(), moderation, &repo).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("skip unsafe repo {}", repo.id());
+                    state.cycle.filtered += 1;
+                    mark_processed(config, state, progress_key.as_deref(), &repo).await?;
+                    continue;
+                }
+                Err(e) => warn!("moderate {} failed, keep it: {:#}", repo.id(), e),
+            }
+        }
+
+        // 所有平台都推送过时仍然交给 broadcast 处理 edit_on_update，只计入统计
+        let pending = config.platforms().is_empty() || has_pending_platform(config, state, &repo).await?;
+        if !pending {
+            state.cycle.deduped += 1;
+        }
+        // 只为还需要推送的项目请求 GitHub API
+        let enrich = config.trending.issue_counts
+            || config.trending.topics
+            || config.trending.author_info
+            || config.trending.updated
+            || config.trending.releases
+            || config.trending.why_trending
+            || config.translate_name;
+        if enrich && pending {
+            let ttl = config.trending.metadata_ttl();
+            if config.trending.issue_counts {
+                if let Err(e) = repo.fetch_issue_counts(state.redis()?.writer(), ttl).await {
+                    warn!("fetch issue counts of {} failed: {:#}", repo.id(), e);
+                }
+            }
+            if config.trending.topics {
+                let max = config.trending.max_topics.unwrap_or(5);
+                if let Err(e) = repo.fetch_topics(state.redis()?.writer(), ttl, max).await {
+                    warn!("fetch topics of {} failed: {:#}", repo.id(), e);
+                }
+            }
+            // 开启 require_recent_release 时过滤阶段已经获取过
+            if config.trending.releases && repo.latest_release.is_empty() {
+                if let Err(e) = repo.fetch_release(state.redis()?.writer(), ttl).await {
+                    warn!("fetch latest release of {} failed: {:#}", repo.id(), e);
+                }
+            }
+            if config.trending.updated {
+                if let Err(e) = repo.fetch_pushed_at(state.redis()?.writer(), ttl).await {
+                    warn!("fetch pushed_at of {} failed: {:#}", repo.id(), e);
+                }
+            }
+            if config.trending.author_info {
+                if let Err(e) = repo.fetch_author_info(state.redis()?.writer(), ttl).await {
+                    warn!("fetch author info of {} failed: {:#}", repo.author, e);
+                }
+            }
+            if config.translate_name && openai::is_configured() {
+                if let Err(e) = repo.fetch_translated_name(state.redis()?.writer(), ttl).await {
+                    warn!("translate name of {} failed: {:#}", repo.id(), e);
+                }
+            }
+            if config.trending.why_trending && openai::is_configured() {
+                let prompt = config.trending.why_trending_prompt.as_deref().unwrap_or(repo::DEFAULT_WHY_TRENDING_PROMPT);
+                if let Err(e) = repo.fetch_why_trending(prompt, state.redis()?.writer(), ttl).await {
+                    warn!("generate why trending of {} failed: {:#}", repo.id(), e);
+                }
+            }
+        }
+
+        let posted = match (&config.schedule, config.post_mode) {
+            _ if config.platforms().is_empty() => {
+                info!("log only: {} ({} stars today) {}", repo.id(), repo.stars_today, repo.description);
+                false
+            }
+            _ if config.preview.is_some() => {
+                send_preview(config, state, &repo).await?
+            }
+            (Some(schedule), _) => {
+                enqueue(config, state, schedule, &repo).await?
+            }
+            (None, config::PostMode::Broadcast) => {
+                broadcast(config, state, &repo).await?
+            }
+            (None, config::PostMode::Failover) => {
+                failover(config, state, &repo).await?
+            }
+        };
+        mark_processed(config, state, progress_key.as_deref(), &repo).await?;
+
+        if posted {
+            state.cycle.posted += 1;
+            *posted_per_author.entry(repo.author.to_lowercase()).or_default() += 1;
+        }
+        if posted && config.dedup_description {
+            let ttl = config.dedup_description_ttl.unwrap_or(config.interval.post_ttl);
+            repo::mark_description_posted(state.redis()?.writer(), &repo, ttl)
+                .await
+                .context("While marking description posted")?;
+        }
+        if posted {
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.interval.post_interval)) => {}
+                _ = heartbeat::shutdown_requested() => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// replay 没有进度 key，不记录
+async fn mark_processed(
+    config: &config::Config,
+    state: &mut State,
+    progress_key: Option<&str>,
+    repo: &repo::Repo,
+) -> Result<()> {
+    let Some(key) = progress_key else {
+        return Ok(());
+    };
+    repo::mark_processed(state.redis()?.writer(), key, &repo.post_id(), progress_ttl(config))
+        .await
+        .context("While saving cycle progress")
+}
+
+// 进度不超过最短的去重时间，去重记录过期后项目可以再次推送，不会因为进度被跳过
+fn progress_ttl(config: &config::Config) -> usize {
+    config
+        .platforms()
+        .iter()
+        .map(|platform| config.interval.dedup_ttl(platform.options().post_ttl))
+        .min()
+        .unwrap_or_else(|| config.interval.dedup_ttl(None))
+}
+
+// 推送 trending developers，与项目推送的流程相互独立
+async fn developers_loop(
+    config: &config::Config,
+    state: &mut State,
+) -> Result<()> {
+    let developers = developer::fetch_developers(&config.trending)
+        .await
+        .context("While fetching developers")?;
+    metrics::inc_fetches();
+    info!("fetched {} developers", developers.len());
+
+    for developer in developers {
+        if heartbeat::is_shutting_down() {
+            info!("shutting down, leaving the remaining developers for the next run");
+            break;
+        }
+        if config.denylist.authors.contains(&developer.username) {
+            continue;
+        }
+        if config.platforms().is_empty() {
+            info!("log only: {} {}", developer.id(), developer.repo_name);
+            continue;
+        }
+
+        let mut posted = false;
+        for platform in config.platforms() {
+            if is_posted(config, state, platform, &developer.id())
+                .await
+                .context("While checking developer posted")?
+            {
+                continue;
+            }
+
+            let content = platform
+                .content_by_developer(&developer)
+                .await
+                .context(format!("While getting {} content", platform.name()))?;
+            let post = schedule::PendingPost {
+                platform: platform.name().to_string(),
+                id: developer.id(),
+                content,
+                summary: None,
+                token_usage: 0,
+                stars: None,
+            };
+            let published = publish(config, state, platform, &post, None).await?;
+            mark_posted(config, state, platform, &developer.id(), "", published.message_id(), None).await?;
+            posted |= published.is_posted();
+        }
+
+        if posted {
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(config.interval.post_interval)) => {}
+                _ = heartbeat::shutdown_requested() => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// 推送到所有未推送过该项目的平台
+async fn broadcast(
+    config: &config::Config,
+    state: &mut State,
+    repo: &repo::Repo,
+) -> Result<bool> {
+    let mut posted = false;
+    let mut media_cache = None;
+    for platform in config.platforms() {
+        if is_posted(config, state, platform, &repo.post_id())
             .await
             .context("While checking repo posted")?
         {
+            if platform.options().edit_on_update {
+                if let Err(e) = edit_post(config, state, platform, repo).await {
+                    error!("{:#}", e);
+                }
+            }
+            continue;
+        }
+        if let Some(message_id) = reply_retrend(state, platform, repo).await {
+            mark_posted(config, state, platform, &repo.post_id(), &repo.language, Some(&message_id), Some(repo.stars)).await?;
+            posted = true;
             continue;
         }
 
-        if let Some(zsxq) = &config.zsxq {
-            let result = zsxq.content_by_repo(&repo).await.context("While getting zsxq content");
-            match result {
-                Ok(content) => {
-                    zsxq.post(&content).await.context("While posting to zsxq")?;
+        let result = platform
+            .content_by_repo(repo)
+            .await
+            .context(format!("While getting {} content", platform.name()));
+        // 生成内容失败或内容过于相似时同样标记，不再重试，但不计入推送数量
+        let published = match result {
+            Ok(content) => {
+                let media = demo_media(platform, repo, &mut media_cache).await;
+                publish(config, state, platform, &schedule::PendingPost::new(platform.name(), repo, content), media).await?
+            }
+            Err(e) => {
+                error!("{:#}", e);
+                state.cycle.errors += 1;
+                Published::Skipped
+            }
+        };
+        if let Some(message_id) = published.message_id() {
+            post_discussion(platform, repo, message_id).await;
+            remember_topic(state, platform, repo, message_id).await;
+        }
+
+        mark_posted(config, state, platform, &repo.post_id(), &repo.language, published.message_id(), Some(repo.stars)).await?;
+        posted |= published.is_posted();
+    }
+    Ok(posted)
+}
+
+// 在 reply_on_retrend 时间内再次上榜时回复到首次推送的消息下，成功时返回该消息 id
+// 消息已被删除等原因回复失败时忘记该消息，重新推送
+async fn reply_retrend(state: &mut State, platform: &dyn Platform, repo: &repo::Repo) -> Option<String> {
+    if platform.options().reply_on_retrend.is_none() || state.file_store.is_some() {
+        return None;
+    }
+    let message_id = match repo::get_topic(state.redis_pool.as_mut()?, platform.name(), &repo.id()).await {
+        Ok(message_id) => message_id?,
+        Err(e) => {
+            error!("get first post of {} on {} failed: {:#}", repo.id(), platform.name(), e);
+            return None;
+        }
+    };
+    let content = platform::format::render_retrend(platform.options(), repo);
+    match platform.reply(&message_id, &content).await {
+        Ok(()) => {
+            info!("replied retrending {} under {} on {}", repo.id(), message_id, platform.name());
+            Some(message_id)
+        }
+        Err(e) => {
+            warn!("reply to {} on {} failed, posting again: {:#}", message_id, platform.name(), e);
+            if let Err(e) = repo::forget_topic(state.redis_pool.as_mut()?.writer(), platform.name(), &repo.id()).await {
+                error!("{:#}", e);
+            }
+            None
+        }
+    }
+}
+
+async fn remember_topic(state: &mut State, platform: &dyn Platform, repo: &repo::Repo, message_id: &str) {
+    let Some(window) = platform.options().reply_on_retrend else {
+        return;
+    };
+    let (None, Some(redis_pool)) = (&state.file_store, state.redis_pool.as_mut()) else {
+        return;
+    };
+    if let Err(e) = repo::set_topic(redis_pool.writer(), platform.name(), &repo.id(), message_id, window as usize).await {
+        error!("remember first post of {} on {} failed: {:#}", repo.id(), platform.name(), e);
+    }
+}
+
+// 再次上榜且 star 增长超过 edit_min_star_delta 时修改原消息
+async fn edit_post(
+    config: &config::Config,
+    state: &mut State,
+    platform: &dyn Platform,
+    repo: &repo::Repo,
+) -> Result<()> {
+    let dedup_id = config.interval.dedup_id(&repo.post_id());
+    let Some(mut record) = repo::get_post_record(state.redis()?, platform.name(), &dedup_id)
+        .await
+        .context("While reading post record")?
+    else {
+        return Ok(());
+    };
+    let (Some(message_id), Some(stars)) = (&record.message_id, record.stars) else {
+        return Ok(());
+    };
+    if repo.stars < stars + platform.options().edit_min_star_delta() {
+        return Ok(());
+    }
+
+    let content = platform
+        .content_by_repo(repo)
+        .await
+        .context(format!("While getting {} content", platform.name()))?;
+    platform
+        .edit(message_id, &content.content)
+        .await
+        .context(format!("While editing {} post", platform.name()))?;
+    record.stars = Some(repo.stars);
+    repo::update_post_record(state.redis()?.writer(), platform.name(), &dedup_id, &record)
+        .await
+        .context("While updating post record")?;
+    info!("edited {} on {}, stars {} -> {}", repo.id(), platform.name(), stars, repo.stars);
+    Ok(())
+}
+
+// 定时推送模式下生成内容并放入队列，入队即视为已推送，避免重复入队
+async fn enqueue(
+    config: &config::Config,
+    state: &mut State,
+    schedule: &config::ScheduleConfig,
+    repo: &repo::Repo,
+) -> Result<bool> {
+    let mut queued = false;
+    for platform in config.platforms() {
+        if is_posted(config, state, platform, &repo.post_id())
+            .await
+            .context("While checking repo posted")?
+        {
+            continue;
+        }
+
+        let content = match platform.content_by_repo(repo).await {
+            Ok(content) => content,
+            Err(e) => {
+                error!("While getting {} content: {:#}", platform.name(), e);
+                continue;
+            }
+        };
+        let post = schedule::PendingPost::new(platform.name(), repo, content);
+        schedule::enqueue(state.redis()?.writer(), schedule, &post)
+            .await
+            .context("While enqueuing scheduled post")?;
+        mark_posted(config, state, platform, &repo.post_id(), &repo.language, None, Some(repo.stars)).await?;
+        queued = true;
+    }
+    Ok(queued)
+}
+
+// 生成各平台的内容发送到预览平台，保存下来等待 approve 子命令
+async fn send_preview(config: &config::Config, state: &mut State, repo: &repo::Repo) -> Result<bool> {
+    let Some(preview) = &config.preview else {
+        return Ok(false);
+    };
+    let Some(preview_platform) = preview.target.platform() else {
+        return Ok(false);
+    };
+    let mut posts = vec![];
+    for platform in config.platforms() {
+        if is_posted(config, state, platform, &repo.post_id())
+            .await
+            .context("While checking repo posted")?
+        {
+            continue;
+        }
+        match platform.content_by_repo(repo).await {
+            Ok(content) => posts.push(schedule::PendingPost::new(platform.name(), repo, content)),
+            Err(e) => error!("While getting {} content: {:#}", platform.name(), e),
+        }
+    }
+    if posts.is_empty() {
+        return Ok(false);
+    }
+
+    for post in posts.iter() {
+        let message = format!(
+            "[待审核] {} -> {}\n\n{}\n\n通过：github-trending approve {}\n拒绝：github-trending reject {}",
+            post.id, post.platform, post.content, post.id, post.id
+        );
+        preview_platform
+            .post(&message)
+            .await
+            .context(format!("While posting preview to {}", preview_platform.name()))?;
+    }
+    approval::save(state.redis()?.writer(), preview, &repo.id(), &posts)
+        .await
+        .context("While saving pending approval")?;
+    // 已预览的项目不再重复生成
+    for platform in config.platforms() {
+        if posts.iter().any(|post| post.platform == platform.name()) {
+            mark_posted(config, state, platform, &repo.post_id(), &repo.language, None, Some(repo.stars)).await?;
+        }
+    }
+    Ok(true)
+}
+
+// 审核通过后推送到正式平台，配置了定时推送时放入队列
+async fn approve(config: &config::Config, state: &mut State, id: &str) -> Result<()> {
+    let Some(preview) = &config.preview else {
+        return Err(anyhow!("preview is not configured"));
+    };
+    let posts = approval::take(state.redis()?.writer(), preview, id)
+        .await
+        .context("While reading pending approval")?;
+    if posts.is_empty() {
+        return Err(anyhow!("no pending approval for {}", id));
+    }
+    for post in posts {
+        if let Some(schedule) = &config.schedule {
+            schedule::enqueue(state.redis()?.writer(), schedule, &post)
+                .await
+                .context("While enqueuing scheduled post")?;
+            continue;
+        }
+        let Some(platform) = config.platforms().into_iter().find(|p| p.name() == post.platform) else {
+            warn!("drop approved post {} for unknown platform {}", post.id, post.platform);
+            continue;
+        };
+        if let Published::Posted(Some(message_id)) = publish(config, state, platform, &post, None).await? {
+            save_message_id(config, state, platform, &post.id, &message_id).await?;
+        }
+    }
+    info!("approved {}", id);
+    Ok(())
+}
+
+async fn reject(config: &config::Config, state: &mut State, id: &str) -> Result<()> {
+    let Some(preview) = &config.preview else {
+        return Err(anyhow!("preview is not configured"));
+    };
+    let posts = approval::take(state.redis()?.writer(), preview, id)
+        .await
+        .context("While reading pending approval")?;
+    if posts.is_empty() {
+        return Err(anyhow!("no pending approval for {}", id));
+    }
+    info!("rejected {}", id);
+    Ok(())
+}
+
+// 重新生成已推送项目的简介，支持编辑的平台修改原消息，其他平台发布一条新消息
+async fn regenerate(config: &config::Config, state: &mut State, id: &str) -> Result<()> {
+    let (author, name) = id
+        .split_once('/')
+        .ok_or_else(|| anyhow!("regenerate requires owner/name, got {}", id))?;
+    let metadata = github::repo_metadata(author, name)
+        .await
+        .context("While fetching repo metadata")?;
+    let repo = repo::Repo {
+        author: author.to_string(),
+        name: name.to_string(),
+        description: metadata.description.unwrap_or_default(),
+        language: metadata.language.unwrap_or_default(),
+        stars: metadata.stargazers_count,
+        ..Default::default()
+    };
+    let mut regenerated = false;
+    for platform in config.platforms() {
+        if !is_posted(config, state, platform, &repo.post_id()).await? {
+            continue;
+        }
+        let content = platform
+            .content_by_repo(&repo)
+            .await
+            .context(format!("While getting {} content", platform.name()))?;
+        // 重新生成的内容不计入周报
+        let post = schedule::PendingPost {
+            stars: None,
+            ..schedule::PendingPost::new(platform.name(), &repo, content)
+        };
+        let dedup_id = config.interval.dedup_id(&repo.post_id());
+        // 没有 Redis 时没有推送记录，直接发布新消息
+        let record = match state.redis_pool.as_mut() {
+            Some(redis_pool) => repo::get_post_record(redis_pool, platform.name(), &dedup_id)
+                .await
+                .context("While reading post record")?,
+            None => None,
+        };
+        if let Some(message_id) = record.and_then(|record| record.message_id) {
+            match platform.edit(&message_id, &post.content).await {
+                Ok(()) => {
+                    info!("regenerated {} on {} in place", id, platform.name());
+                    regenerated = true;
+                    continue;
+                }
+                Err(e) if e.is::<Unsupported>() => {}
+                Err(e) => return Err(e.context(format!("While editing {} post", platform.name()))),
+            }
+        }
+        if let Published::Posted(Some(message_id)) = publish(config, state, platform, &post, None).await? {
+            save_message_id(config, state, platform, id, &message_id).await?;
+        }
+        info!("regenerated {} on {} as a new post", id, platform.name());
+        regenerated = true;
+    }
+    if !regenerated {
+        return Err(anyhow!("{} has not been posted on any platform", id));
+    }
+    Ok(())
+}
+
+// 到达配置的时间时推送本周汇总，当前 star 数优先使用本轮获取的 trending，其他项目查询 GitHub
+async fn send_recap(
+    config: &config::Config,
+    state: &mut State,
+    recap: &config::RecapConfig,
+    repos: &[repo::Repo],
+) -> Result<()> {
+    let now = config.interval.local_now();
+    let conn = state.redis()?.writer();
+    if !recap::is_due(conn, recap, now).await.context("While checking recap")? {
+        return Ok(());
+    }
+    let Some(platform) = recap.target.platform() else {
+        return Ok(());
+    };
+    let posted = recap::posted(conn, recap, now).await.context("While reading recap")?;
+    let mut current: HashMap<String, u64> = repos.iter().map(|repo| (repo.id(), repo.stars)).collect();
+    for id in posted.keys() {
+        if current.contains_key(id) {
+            continue;
+        }
+        let Some((author, name)) = id.split_once('/') else {
+            continue;
+        };
+        match github::repo_metadata(author, name).await {
+            Ok(metadata) => {
+                current.insert(id.clone(), metadata.stargazers_count);
+            }
+            Err(e) => warn!("get stars of {} for recap failed: {:#}", id, e),
+        }
+    }
+    let top = recap::top(recap, &posted, &current);
+    if top.is_empty() {
+        return Ok(());
+    }
+    platform
+        .post(&recap::compose(recap, &top))
+        .await
+        .context(format!("While posting recap to {}", platform.name()))?;
+    recap::mark_sent(state.redis()?.writer(), recap, now).await.context("While saving recap")?;
+    info!("posted weekly recap to {}", platform.name());
+    Ok(())
+}
+
+// 长时间没有成功推送时，重建可能处于半断开状态的 HTTP 客户端和 Redis 连接
+async fn check_watchdog(config: &config::Config, state: &mut State) {
+    let Some(watchdog) = config.interval.watchdog else {
+        return;
+    };
+    if state.last_success.elapsed() < Duration::from_secs(watchdog) {
+        return;
+    }
+    warn!("no successful post in {}s, re-initializing clients", watchdog);
+    http::reset_client();
+    if let Some(redis) = &config.redis {
+        match pool::RedisPool::connect(&redis.url.urls()).await {
+            Ok(redis_pool) => state.redis_pool = Some(redis_pool),
+            Err(e) => error!("reconnect redis failed: {:#}", e),
+        }
+    }
+    // 重新计时，避免每轮都重建
+    state.last_success = Instant::now();
+}
+
+// 删除推送时间超过 delete_after 的消息
+async fn delete_stale(config: &config::Config, state: &mut State) {
+    let Some(redis_pool) = state.redis_pool.as_mut() else {
+        return;
+    };
+    for platform in config.platforms() {
+        let Some(delete_after) = platform.options().delete_after else {
+            continue;
+        };
+        let before = repo::now_ts().saturating_sub(delete_after);
+        let messages = match repo::stale_messages(redis_pool.writer(), platform.name(), before).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("While reading stale {} posts: {:#}", platform.name(), e);
+                continue;
+            }
+        };
+        for message_id in messages.iter() {
+            match platform.delete(message_id).await {
+                Ok(()) => info!("deleted stale post {} on {}", message_id, platform.name()),
+                Err(e) if e.is::<Unsupported>() => {
+                    // 不支持删除时丢弃所有记录，避免每轮重复尝试
+                    warn!("{}, skip delete_after", e);
+                    for message_id in messages.iter() {
+                        if let Err(e) = repo::forget_message(redis_pool.writer(), platform.name(), message_id).await {
+                            error!("{:#}", e);
+                        }
+                    }
+                    break;
                 }
                 Err(e) => {
-                    error!("{:#}", e);
+                    error!("delete post {} on {} failed: {:#}", message_id, platform.name(), e);
+                    continue;
                 }
             }
-            // zsxq.post(&content).await.context("While posting to zsxq")?;
+            if let Err(e) = repo::forget_message(redis_pool.writer(), platform.name(), message_id).await {
+                error!("{:#}", e);
+            }
         }
+    }
+}
 
-        repo::mark_posted_repo(redis_conn, &repo, config.interval.post_ttl)
+// 到达推送时间时释放队列中的内容
+async fn release_scheduled(
+    config: &config::Config,
+    state: &mut State,
+    schedule: &config::ScheduleConfig,
+) -> Result<()> {
+    let posts = schedule::take_due(state.redis()?.writer(), schedule, config.interval.local_now())
+        .await
+        .context("While reading scheduled posts")?;
+    let mut failed = vec![];
+    for (i, due) in posts.into_iter().enumerate() {
+        // 收到退出信号时剩余的内容放回队列
+        if heartbeat::is_shutting_down() {
+            failed.push(due);
+            continue;
+        }
+        if i > 0 {
+            tokio::time::sleep(Duration::from_secs(config.interval.post_interval)).await;
+        }
+        let post = &due.post;
+        let Some(platform) = config.platforms().into_iter().find(|p| p.name() == post.platform) else {
+            warn!("drop scheduled post {} for unknown platform {}", post.id, post.platform);
+            schedule::done(state.redis()?.writer(), schedule, &due)
+                .await
+                .context("While removing scheduled post")?;
+            continue;
+        };
+        match publish(config, state, platform, post, None).await {
+            Ok(published) => {
+                schedule::done(state.redis()?.writer(), schedule, &due)
+                    .await
+                    .context("While removing scheduled post")?;
+                if let Some(message_id) = published.message_id() {
+                    save_message_id(config, state, platform, &post.id, message_id).await?;
+                }
+            }
+            Err(e) => {
+                error!("{:#}, retry at the next slot", e);
+                state.cycle.errors += 1;
+                failed.push(due);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        schedule::requeue(state.redis()?.writer(), schedule, &failed)
             .await
-            .context("While marking repo posted")?;
+            .context("While requeuing scheduled posts")?;
+    }
+    Ok(())
+}
 
-        info!("posted {} - {}", repo.author, repo.name);
+// 按顺序尝试各个平台，第一个推送成功后停止
+async fn failover(
+    config: &config::Config,
+    state: &mut State,
+    repo: &repo::Repo,
+) -> Result<bool> {
+    let chain = config.failover_chain();
+    for platform in chain.iter() {
+        if is_posted(config, state, *platform, &repo.post_id())
+            .await
+            .context("While checking repo posted")?
+        {
+            return Ok(false);
+        }
+    }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(
-            config.interval.post_interval,
-        ))
-            .await;
+    let mut media_cache = None;
+    for platform in chain {
+        let result = match platform
+            .content_by_repo(repo)
+            .await
+            .context(format!("While getting {} content", platform.name()))
+        {
+            Ok(content) => {
+                let media = demo_media(platform, repo, &mut media_cache).await;
+                publish(config, state, platform, &schedule::PendingPost::new(platform.name(), repo, content), media).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(published) => {
+                if let Some(message_id) = published.message_id() {
+                    post_discussion(platform, repo, message_id).await;
+                }
+                mark_posted(config, state, platform, &repo.post_id(), &repo.language, published.message_id(), Some(repo.stars)).await?;
+                return Ok(published.is_posted());
+            }
+            Err(e) => {
+                error!("{:#}", e);
+                state.cycle.errors += 1;
+            }
+        }
     }
 
+    error!("all platforms failed for {} - {}", repo.author, repo.name);
+    Ok(false)
+}
+
+// 在主消息的讨论区回复完整内容，失败时只记录日志，不影响主消息的推送记录
+async fn post_discussion(platform: &dyn Platform, repo: &repo::Repo, message_id: &str) {
+    let result = match platform.discussion_content_by_repo(repo).await {
+        Ok(Some(content)) => platform.reply(message_id, &content).await,
+        Ok(None) => return,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        error!("post discussion of {} on {} failed: {:#}", repo.id(), platform.name(), e);
+    }
+}
+
+// 演示图片只在第一个需要的平台推送前获取一次，获取失败时只推送文字
+async fn demo_media<'a>(
+    platform: &dyn Platform,
+    repo: &repo::Repo,
+    cache: &'a mut Option<Option<repo::DemoMedia>>,
+) -> Option<&'a repo::DemoMedia> {
+    if !platform.options().attach_demo_media {
+        return None;
+    }
+    if cache.is_none() {
+        let media = repo.demo_media().await.unwrap_or_else(|e| {
+            warn!("get demo media of {} failed: {:#}", repo.id(), e);
+            None
+        });
+        *cache = Some(media);
+    }
+    cache.as_ref().and_then(|media| media.as_ref())
+}
+
+// publish 的结果，Skipped 表示简介与最近推送的其他项目过于相似，没有推送
+enum Published {
+    Posted(Option<String>),
+    Skipped,
+}
+
+impl Published {
+    fn message_id(&self) -> Option<&str> {
+        match self {
+            Published::Posted(message_id) => message_id.as_deref(),
+            Published::Skipped => None,
+        }
+    }
+
+    fn is_posted(&self) -> bool {
+        matches!(self, Published::Posted(_))
+    }
+}
+
+async fn publish(
+    config: &config::Config,
+    state: &mut State,
+    platform: &dyn Platform,
+    post: &schedule::PendingPost,
+    media: Option<&repo::DemoMedia>,
+) -> Result<Published> {
+    let (id, content, summary) = (post.id.as_str(), post.content.as_str(), post.summary.as_deref());
+    // 只比较简介，模板、链接和标签在每条推送中都相同；没有简介的内容（例如开发者推送）不检查
+    if let (Some(similarity), Some(summary)) = (&config.similarity, summary) {
+        let score = similarity::max_similarity(state.redis()?.writer(), similarity, id, summary)
+            .await
+            .context("While checking content similarity")?;
+        if score > similarity.threshold {
+            info!(
+                "skip {} on {}, {:.0}% similar to a recent post",
+                id, platform.name(), score * 100.0
+            );
+            return Ok(Published::Skipped);
+        }
+    }
+    wait_post_gap(platform, &state.last_posted_at).await;
+    let message_id = match media {
+        Some(media) => platform.post_with_media(content, media).await,
+        None => platform.post(content).await,
+    }
+    .context(format!("While posting to {}", platform.name()))?;
+    state.last_posted_at.insert(platform.name().to_string(), Instant::now());
+    state.last_success = Instant::now();
+    metrics::inc_posts();
+    if let Some(audit) = &config.audit {
+        audit::record(state.redis()?.writer(), audit, id, platform.name(), content, post.token_usage)
+            .await
+            .context("While writing audit log")?;
+    }
+    if let (Some(recap), Some(stars)) = (&config.recap, post.stars) {
+        recap::record(state.redis()?.writer(), recap, repo::Repo::id_of_post(id), stars, config.interval.local_now())
+            .await
+            .context("While recording recap")?;
+    }
+    if let (Some(similarity), Some(summary)) = (&config.similarity, summary) {
+        similarity::remember(state.redis()?.writer(), similarity, id, summary)
+            .await
+            .context("While remembering posted content")?;
+    }
+    if let Some(message_id) = &message_id {
+        if platform.options().delete_after.is_some() {
+            repo::remember_message(state.redis()?.writer(), platform.name(), message_id)
+                .await
+                .context("While remembering message id")?;
+        }
+        if platform.options().pin_top && state.pinned.insert(platform.name().to_string()) {
+            if let Err(e) = pin_top(state, platform, message_id).await {
+                error!("pin post on {} failed: {:#}", platform.name(), e);
+            }
+        }
+    }
+    Ok(Published::Posted(message_id))
+}
+
+// 置顶本轮第一条推送，并取消上一次的置顶
+async fn pin_top(state: &mut State, platform: &dyn Platform, message_id: &str) -> Result<()> {
+    let conn = state.redis()?.writer();
+    if let Some(previous) = repo::get_pinned(conn, platform.name()).await? {
+        if previous != message_id {
+            platform.unpin(&previous).await.context("While unpinning previous post")?;
+        }
+    }
+    platform.pin(message_id).await.context("While pinning post")?;
+    repo::set_pinned(conn, platform.name(), message_id).await?;
+    info!("pinned {} on {}", message_id, platform.name());
+    Ok(())
+}
+
+async fn has_pending_platform(config: &config::Config, state: &mut State, repo: &repo::Repo) -> Result<bool> {
+    for platform in config.platforms() {
+        if !is_posted(config, state, platform, &repo.post_id())
+            .await
+            .context("While checking repo posted")?
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+async fn is_posted(config: &config::Config, state: &mut State, platform: &dyn Platform, id: &str) -> Result<bool> {
+    let dedup_id = config.interval.dedup_id(id);
+    if let Some(file_store) = &state.file_store {
+        return Ok(file_store.contains(&repo::posted_key(platform.name(), &dedup_id)));
+    }
+    repo::is_posted(state.redis()?, platform.name(), &dedup_id).await
+}
+
+async fn mark_posted(
+    config: &config::Config,
+    state: &mut State,
+    platform: &dyn Platform,
+    id: &str,
+    language: &str,
+    message_id: Option<&str>,
+    stars: Option<u64>,
+) -> Result<()> {
+    let ttl = config.interval.dedup_ttl(platform.options().post_ttl);
+    let dedup_id = config.interval.dedup_id(id);
+    match &mut state.file_store {
+        Some(file_store) => file_store
+            .insert(&repo::posted_key(platform.name(), &dedup_id), ttl)
+            .context("While marking repo posted")?,
+        None => repo::mark_posted(state.redis()?.writer(), platform.name(), &dedup_id, language, message_id, stars, ttl)
+            .await
+            .context("While marking repo posted")?,
+    }
+    info!("posted {} to {}", id, platform.name());
     Ok(())
 }
 
+async fn save_message_id(
+    config: &config::Config,
+    state: &mut State,
+    platform: &dyn Platform,
+    id: &str,
+    message_id: &str,
+) -> Result<()> {
+    let Some(redis_pool) = state.redis_pool.as_mut() else {
+        return Ok(());
+    };
+    repo::set_message_id(redis_pool, platform.name(), &config.interval.dedup_id(id), message_id)
+        .await
+        .context("While saving message id")
+}
+
+// 保证同一平台两次推送之间至少间隔 min_post_gap 秒
+async fn wait_post_gap(platform: &dyn Platform, last_posted_at: &HashMap<String, Instant>) {
+    let (Some(gap), Some(last)) = (platform.options().min_post_gap, last_posted_at.get(platform.name())) else {
+        return;
+    };
+    let gap = Duration::from_secs(gap);
+    let elapsed = last.elapsed();
+    if elapsed < gap {
+        tokio::time::sleep(gap - elapsed).await;
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::try_init().context("While initializing env_logger")?;
+    let mut once = false;
+    let mut show_stats = false;
+    let mut self_test = false;
+    let mut verbose = 0;
+    let mut approve_id = None;
+    let mut reject_id = None;
+    let mut regenerate_id = None;
+    let mut replay = false;
+    let mut replay_date = None;
+    let mut config_file_path = "./config.toml".to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--once" => once = true,
+            "stats" => show_stats = true,
+            "approve" => approve_id = args.next(),
+            "reject" => reject_id = args.next(),
+            "regenerate" => regenerate_id = args.next(),
+            "replay" => replay = true,
+            "--date" => replay_date = args.next(),
+            "--self-test" => self_test = true,
+            "--verbose" => verbose += 1,
+            _ if arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v') => {
+                verbose += arg.len() - 1
+            }
+            _ => config_file_path = arg,
+        }
+    }
+
+    // 每个 -v 提升一级日志级别，会覆盖 RUST_LOG 的设置
+    let mut logger = env_logger::Builder::from_default_env();
+    let level = match verbose {
+        0 => None,
+        1 => Some(LevelFilter::Info),
+        2 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    };
+    if let Some(level) = level {
+        logger.filter_level(level);
+    }
+    logger.try_init().context("While initializing env_logger")?;
 
-    let mut args = std::env::args();
-    args.next();
-    let config_file_path = args.next().unwrap_or_else(|| "./config.toml".to_string());
-    let config = config::read_file(&config_file_path).context("While reading config file")?;
+    if self_test {
+        return repo::self_test().await;
+    }
 
-    let redis_client =
-        redis::Client::open(config.redis.url.as_str()).context("While creating redis client")?;
-    let mut redis_conn = redis_client
-        .get_async_connection()
-        .await
-        .context("While connecting redis")?;
+    let mut config = config::load(&config_file_path).await.context("While reading config file")?;
+    config.validate().context("While validating config file")?;
+    openai::configure(config.openai.clone());
+    github::configure(config.trending.max_rate_limit_wait);
 
+    let mut redis_pool = match &config.redis {
+        Some(redis) => Some(pool::RedisPool::connect(&redis.url.urls()).await?),
+        None => None,
+    };
+
+    if !openai::is_configured() {
+        warn!("OPENAI_API_KEY is not set, summarization is disabled, posting plain descriptions");
+    }
+
+    if show_stats {
+        let Some(redis_pool) = redis_pool.as_mut() else {
+            bail!("stats requires [redis]");
+        };
+        return stats::run(&config, redis_pool.writer()).await;
+    }
+
+    let mut state = State {
+        redis_pool,
+        last_posted_at: HashMap::new(),
+        pinned: HashSet::new(),
+        last_success: Instant::now(),
+        cycle: server::CycleReport::default(),
+        file_store: match &config.store {
+            store::StoreConfig::Redis => None,
+            store::StoreConfig::File { path } => Some(store::FileStore::open(path).context("While opening file store")?),
+        },
+    };
+    if let Some(id) = approve_id {
+        return approve(&config, &mut state, &id).await;
+    }
+    if let Some(id) = reject_id {
+        return reject(&config, &mut state, &id).await;
+    }
+    if let Some(id) = regenerate_id {
+        return regenerate(&config, &mut state, &id).await;
+    }
+    if replay {
+        let date = replay_date.ok_or_else(|| anyhow!("replay requires --date YYYY-MM-DD"))?;
+        return replay_day(&config, &mut state, &date).await;
+    }
+    let mut alerter = alert::Alerter::default();
+
+    // 单次运行模式，适用于 cron / GitHub Actions，失败时以非零状态码退出
+    // 单个项目推送失败不会中断本轮，结束后按本轮的失败次数返回错误
+    if once {
+        let res = main_loop(&config, &mut state).await.and_then(|()| match state.cycle.errors {
+            0 => Ok(()),
+            errors => Err(anyhow!("{} posts failed in this cycle", errors)),
+        });
+        if let Err(e) = &res {
+            metrics::inc_errors();
+            if let Some(alert) = &config.alert {
+                alerter.send(alert, e).await;
+            }
+        }
+        write_metrics(&config);
+        return res;
+    }
+
+    // 心跳只在常驻运行时发送，修改后需要重启才会生效
+    let heartbeat = config.heartbeat.clone();
+    if let Some(heartbeat) = &heartbeat {
+        heartbeat::started(&config, heartbeat).await;
+        heartbeat::spawn_shutdown()?;
+    }
+    let mut hangup = signal(SignalKind::hangup()).context("While listening SIGHUP")?;
+    // 未配置 server 时 trigger_tx 不会发送，只用于保持通道打开
+    let (trigger_tx, mut triggers) = mpsc::channel(1);
+    if let Some(server) = config.server.clone() {
+        let trigger_tx = trigger_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server::serve(server, trigger_tx).await {
+                error!("http server stopped: {:#}", e);
+            }
+        });
+    }
+    if config.interval.pipeline {
+        run_pipeline(&mut config, &mut state, &config_file_path, &mut alerter, &mut hangup, &mut triggers).await?;
+    } else {
+        run_loop(&mut config, &mut state, &config_file_path, &mut alerter, &mut hangup, &mut triggers).await;
+    }
+    if let Some(heartbeat) = &heartbeat {
+        heartbeat::stopped(heartbeat).await;
+    }
+    Ok(())
+}
+
+// 按 fetch_interval 定时执行，收到退出信号时在当前轮次结束后返回
+async fn run_loop(
+    config: &mut config::Config,
+    state: &mut State,
+    config_file_path: &str,
+    alerter: &mut alert::Alerter,
+    hangup: &mut Signal,
+    triggers: &mut mpsc::Receiver<server::Trigger>,
+) {
     loop {
-        let res = main_loop(&config, &mut redis_conn).await;
-        if let Err(e) = res {
-            error!("{:#}", e);
+        let res = main_loop(config, state).await;
+        finish_cycle(config, state, alerter, res).await;
+        if heartbeat::is_shutting_down() {
+            return;
         }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(
+        let sleep = tokio::time::sleep(tokio::time::Duration::from_secs(
             config.interval.fetch_interval,
-        ))
-            .await;
+        ));
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                _ = heartbeat::shutdown_requested() => return,
+                _ = hangup.recv() => reload_config(config_file_path, config).await,
+                // 手动触发的一轮不影响下一次定时获取的时间
+                Some(reply) = triggers.recv() => {
+                    info!("cycle triggered over http");
+                    let res = main_loop(config, state).await;
+                    let error = finish_cycle(config, state, alerter, res).await;
+                    let _ = reply.send(state.cycle.finish(error));
+                }
+            }
+        }
+    }
+}
+
+fn write_metrics(config: &config::Config) {
+    if let Some(path) = &config.metrics_file {
+        if let Err(e) = metrics::write(path) {
+            error!("write metrics file {} failed: {:#}", path, e);
+        }
+    }
+}
+
+// 收到 SIGHUP 时重新读取配置文件，校验失败则继续使用旧配置
+async fn reload_config(path: &str, config: &mut config::Config) {
+    let result = config::load(path).await.and_then(|new_config| {
+        new_config.validate()?;
+        // Redis 连接只在启动时建立
+        if config.redis.is_none() && new_config.redis.is_some() {
+            bail!("[redis] was not configured at startup, restart to enable it");
+        }
+        Ok(new_config)
+    });
+    match result {
+        Ok(new_config) => {
+            openai::configure(new_config.openai.clone());
+            github::configure(new_config.trending.max_rate_limit_wait);
+            *config = new_config;
+            info!("reloaded config from {}", path);
+        }
+        Err(e) => {
+            error!("keep the old config, failed to reload {}: {:#}", path, e);
+        }
     }
 }
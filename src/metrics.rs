@@ -0,0 +1,44 @@
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use anyhow::Result;
+use serde::Serialize;
+use crate::repo::now_ts;
+
+// 进程启动以来的累计计数
+static POSTS: AtomicU64 = AtomicU64::new(0);
+static FETCHES: AtomicU64 = AtomicU64::new(0);
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+
+pub fn inc_posts() {
+    POSTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn inc_fetches() {
+    FETCHES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn inc_errors() {
+    ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Serialize)]
+struct Snapshot {
+    posts: u64,
+    fetches: u64,
+    errors: u64,
+    last_run: u64,
+}
+
+// 先写入临时文件再重命名，避免外部脚本读到写了一半的文件
+pub fn write(path: &str) -> Result<()> {
+    let snapshot = Snapshot {
+        posts: POSTS.load(Ordering::Relaxed),
+        fetches: FETCHES.load(Ordering::Relaxed),
+        errors: ERRORS.load(Ordering::Relaxed),
+        last_run: now_ts(),
+    };
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, serde_json::to_string_pretty(&snapshot)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use crate::config::ModerationConfig;
+use crate::openai::{self, chat_completion, read_url};
+use crate::repo::Repo;
+
+// 提交给模型的 README 最大字符数
+const MAX_README_CHARS: usize = 4000;
+
+// 判断项目是否可以推送，结果缓存在 Redis 中
+pub async fn is_safe(
+    conn: &mut redis::aio::Connection,
+    config: &ModerationConfig,
+    repo: &Repo,
+) -> Result<bool> {
+    let key = format!("{}:{}", config.key_prefix, repo.id());
+    let cached: Option<String> = conn.get(&key).await?;
+    if let Some(verdict) = cached {
+        return Ok(verdict == "safe");
+    }
+
+    let safe = classify(config, repo).await?;
+    let verdict = if safe { "safe" } else { "unsafe" };
+    conn.set_ex::<_, _, ()>(&key, verdict, config.cache_ttl).await?;
+    Ok(safe)
+}
+
+// 先按关键词判断，未命中且配置了模型时再让模型分类
+async fn classify(config: &ModerationConfig, repo: &Repo) -> Result<bool> {
+    let text = format!("{} {} {}", repo.author, repo.name, repo.description).to_lowercase();
+    if config.keywords.iter().any(|keyword| text.contains(&keyword.to_lowercase())) {
+        return Ok(false);
+    }
+    if !openai::is_configured() {
+        return Ok(true);
+    }
+
+    let readme = read_url(&repo.get_url()).await.context("While read url")?;
+    let readme: String = readme.chars().take(MAX_README_CHARS).collect();
+    let prompt = format!("{}\n\n描述：{}\n\n{}", config.prompt, repo.description, readme);
+    let answer = chat_completion(&prompt).await.context("While classifying repo")?.content;
+    Ok(!answer.to_lowercase().contains("unsafe"))
+}
@@ -1,35 +1,172 @@
+use std::collections::HashMap;
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use log::warn;
+use once_cell::sync::Lazy;
 use reqwest::{self, StatusCode};
 use serde_json::{json, Value};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use reqwest_middleware::ClientBuilder;
 use reqwest_retry::policies::ExponentialBackoff;
-use reqwest_retry::RetryTransientMiddleware;
+use reqwest_retry::{
+    default_on_request_failure, default_on_request_success, Retryable, RetryableStrategy, RetryTransientMiddleware,
+};
+use crate::config::{OpenAIAuth, OpenAIConfig};
+use crate::http;
 
-pub async fn chat_completion(content: &str) -> Result<String> {
+// 模型的输出和本次请求消耗的 token 数，total_tokens 供审计日志使用
+#[derive(Default)]
+pub struct Completion {
+    pub content: String,
+    pub total_tokens: u64,
+}
+
+// 配置文件中的 [openai] 配置，启动和重新加载配置时更新
+static CONFIG: Lazy<RwLock<OpenAIConfig>> = Lazy::new(|| RwLock::new(OpenAIConfig::default()));
+
+pub fn configure(config: OpenAIConfig) {
+    *CONFIG.write().unwrap() = config;
+}
+
+fn api_base() -> String {
+    env::var("OPENAI_API_BASE").unwrap_or(String::from("https://api.openai-all.com/v1"))
+}
+
+// 配置文件中的 openai.api_keys，未配置时使用 OPENAI_API_KEY
+fn api_keys() -> Vec<String> {
+    let keys = CONFIG.read().unwrap().api_keys.clone();
+    if keys.is_empty() {
+        env::var("OPENAI_API_KEY").map(|key| vec![key]).unwrap_or_default()
+    } else {
+        keys
+    }
+}
+
+pub fn summary_prompt() -> String {
+    CONFIG.read().unwrap().summary_prompt.clone()
+}
+
+// 未设置 API Key 且不是本地模型服务时，不进行 AI 摘要
+pub fn is_configured() -> bool {
+    !api_keys().is_empty() || is_local(&api_base())
+}
+
+// 轮询使用多个 API Key，返回 401 或额度不足的 Key 在冷却期内跳过
+static KEY_INDEX: AtomicUsize = AtomicUsize::new(0);
+static KEY_COOLDOWNS: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn next_api_key(keys: &[String]) -> Option<String> {
+    let cooldowns = KEY_COOLDOWNS.lock().unwrap();
+    (0..keys.len())
+        .map(|_| &keys[KEY_INDEX.fetch_add(1, Ordering::Relaxed) % keys.len()])
+        .find(|key| cooldowns.get(*key).is_none_or(|until| Instant::now() >= *until))
+        .cloned()
+}
+
+fn cool_down(key: &str, secs: u64) {
+    warn!("openai api key ...{} is cooling down for {}s", key_suffix(key), secs);
+    KEY_COOLDOWNS
+        .lock()
+        .unwrap()
+        .insert(key.to_string(), Instant::now() + Duration::from_secs(secs));
+}
+
+fn key_suffix(key: &str) -> &str {
+    &key[key.len().saturating_sub(4)..]
+}
+
+pub async fn chat_completion(content: &str) -> Result<Completion> {
+    completion(content, false).await
+}
+
+// 要求模型返回 JSON 对象，提示词中需要说明 JSON 的结构
+pub async fn chat_completion_json(content: &str) -> Result<Completion> {
+    completion(content, true).await
+}
+
+async fn completion(content: &str, json_mode: bool) -> Result<Completion> {
+    let keys = api_keys();
+    // 本地模型服务（Ollama、LM Studio 等）通常不需要 API Key
+    if keys.is_empty() {
+        if is_local(&api_base()) {
+            return request_completion(content, None, json_mode).await;
+        }
+        bail!("OPENAI_API_KEY or openai.api_keys is not set");
+    }
+
+    let cooldown = CONFIG.read().unwrap().key_cooldown;
+    for _ in 0..keys.len() {
+        let Some(key) = next_api_key(&keys) else {
+            break;
+        };
+        match request_completion(content, Some(&key), json_mode).await {
+            Err(e) if is_key_exhausted(&e) => cool_down(&key, cooldown),
+            result => return result,
+        }
+    }
+    bail!("all openai api keys are cooling down")
+}
+
+// 401 表示 Key 无效，429 表示限流或额度不足
+fn is_key_exhausted(err: &anyhow::Error) -> bool {
+    matches!(
+        http::error_status(err),
+        Some(StatusCode::UNAUTHORIZED | StatusCode::TOO_MANY_REQUESTS)
+    )
+}
+
+// 429 不在同一个 Key 上重试，由 completion 让该 Key 冷却并立即换下一个 Key
+struct RateLimitNotRetryable;
+
+impl RetryableStrategy for RateLimitNotRetryable {
+    fn handle(&self, res: &Result<reqwest::Response, reqwest_middleware::Error>) -> Option<Retryable> {
+        match res {
+            Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => None,
+            Ok(resp) => default_on_request_success(resp),
+            Err(e) => default_on_request_failure(e),
+        }
+    }
+}
+
+async fn request_completion(content: &str, api_key: Option<&str>, json_mode: bool) -> Result<Completion> {
     // Call the OpenAI API to translate the content to Chinese
     // Replace the following placeholders with your OpenAI API credentials and endpoint
-    let api_key = env::var("OPENAI_API_KEY")?;
-    let api_base = env::var("OPENAI_API_BASE").unwrap_or(String::from("https://api.openai-all.com/v1"));
+    let api_base = api_base();
     let model = env::var("OPENAI_MODEL").unwrap_or(String::from("gemini-1.5-pro"));
     let url = format!("{}/chat/completions", api_base);
+    let config = CONFIG.read().unwrap().clone();
 
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let client = ClientBuilder::new(reqwest::Client::new())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+    let client = ClientBuilder::new(http::client())
+        .with(RetryTransientMiddleware::new_with_policy_and_strategy(retry_policy, RateLimitNotRetryable))
         .build();
 
-    let resp = client
-        .post(url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .json(&json!({
-            "model": model,
-            "messages": [
-                {"role": "user", "content": content}
-            ],
-        }))
-        .send()
-        .await?;
+    let mut req = client.post(url);
+    if let Some(api_key) = api_key {
+        req = match config.auth {
+            OpenAIAuth::Bearer => req.header("Authorization", format!("Bearer {}", api_key)),
+            // Azure OpenAI 使用 api-key 请求头
+            OpenAIAuth::Azure => req.header("api-key", api_key),
+        };
+    }
+    if let Some(api_version) = &config.api_version {
+        req = req.query(&[("api-version", api_version)]);
+    }
+    for (name, value) in config.extra_headers.iter() {
+        req = req.header(name, value);
+    }
+    let mut body = json!({
+        "model": model,
+        "messages": [
+            {"role": "user", "content": content}
+        ],
+    });
+    if json_mode {
+        body["response_format"] = json!({"type": "json_object"});
+    }
+    let resp = req.json(&body).send().await?;
 
     let resp = resp.error_for_status()?;
     if resp.status() != StatusCode::OK {
@@ -37,17 +174,70 @@ pub async fn chat_completion(content: &str) -> Result<String> {
     }
 
     let resp = resp.json::<Value>().await?;
-    let result = resp["choices"][0]["message"]["content"]
-        .as_str()
-        .unwrap_or_default()
-        .to_string();
-    Ok(result)
+    Ok(Completion {
+        content: extract_content(&resp),
+        total_tokens: resp["usage"]["total_tokens"].as_u64().unwrap_or_default(),
+    })
+}
+
+// 移除模型输出中的客套话和包裹内容的 ``` 代码块
+pub fn clean_output(content: &str) -> String {
+    strip_output(content, &CONFIG.read().unwrap().preamble_patterns)
+}
+
+fn strip_output(content: &str, preamble_patterns: &[String]) -> String {
+    let mut lines: Vec<&str> = content.trim().lines().collect();
+    if let Some(first) = lines.first() {
+        let first = first.trim().to_lowercase();
+        if preamble_patterns.iter().any(|p| first.starts_with(&p.to_lowercase())) {
+            lines.remove(0);
+            while lines.first().is_some_and(|line| line.trim().is_empty()) {
+                lines.remove(0);
+            }
+        }
+    }
+    if lines.first().is_some_and(|line| line.trim_start().starts_with("```")) {
+        lines.remove(0);
+        if lines.last().is_some_and(|line| line.trim() == "```") {
+            lines.pop();
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn is_local(api_base: &str) -> bool {
+    url::Url::parse(api_base)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| matches!(host, "localhost" | "127.0.0.1" | "::1" | "[::1]")))
+        .unwrap_or(false)
+}
+
+// 兼容部分 OpenAI 兼容服务返回的不同结构
+fn extract_content(resp: &Value) -> String {
+    [
+        &resp["choices"][0]["message"]["content"],
+        &resp["choices"][0]["text"],
+        &resp["message"]["content"],
+        &resp["response"],
+    ]
+    .iter()
+    .find_map(|v| content_text(v))
+    .unwrap_or_default()
+}
+
+// content 可能是字符串，也可能是 [{"type": "text", "text": "..."}] 形式的数组
+fn content_text(content: &Value) -> Option<String> {
+    if let Some(text) = content.as_str() {
+        return Some(text.to_string());
+    }
+    let parts = content.as_array()?;
+    Some(parts.iter().filter_map(|part| part["text"].as_str()).collect())
 }
 
 // 调用 r.jina.ai 接口读取 github repo 地址的内容
 pub async fn read_url(url: &str) -> Result<String> {
     let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let client = ClientBuilder::new(reqwest::Client::new())
+    let client = ClientBuilder::new(http::client())
         .with(RetryTransientMiddleware::new_with_policy(retry_policy))
         .build();
 
@@ -60,3 +250,53 @@ pub async fn read_url(url: &str) -> Result<String> {
         .await?;
     Ok(resp)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_content, strip_output};
+    use serde_json::json;
+
+    fn patterns() -> Vec<String> {
+        vec!["Here's".to_string(), "以下是".to_string()]
+    }
+
+    #[test]
+    fn test_strip_fenced_output() {
+        let content = "```markdown\n**servo** 是一个浏览器引擎。\n\n支持并行渲染。\n```";
+        assert_eq!(
+            strip_output(content, &patterns()),
+            "**servo** 是一个浏览器引擎。\n\n支持并行渲染。"
+        );
+    }
+
+    #[test]
+    fn test_strip_preamble_output() {
+        let content = "以下是该项目的简介：\n\nservo 是一个浏览器引擎。";
+        assert_eq!(strip_output(content, &patterns()), "servo 是一个浏览器引擎。");
+
+        let content = "Here's a summary:\n```\nservo 是一个浏览器引擎。\n```\n";
+        assert_eq!(strip_output(content, &patterns()), "servo 是一个浏览器引擎。");
+    }
+
+    #[test]
+    fn test_keep_plain_output() {
+        let content = "servo 是一个浏览器引擎。\n```rust\nfn main() {}\n```";
+        assert_eq!(strip_output(content, &patterns()), content);
+    }
+
+    #[test]
+    fn test_extract_string_content() {
+        let resp = json!({"choices": [{"message": {"role": "assistant", "content": "servo 是一个浏览器引擎。"}}]});
+        assert_eq!(extract_content(&resp), "servo 是一个浏览器引擎。");
+    }
+
+    #[test]
+    fn test_extract_array_content() {
+        let resp = json!({"choices": [{"message": {"role": "assistant", "content": [
+            {"type": "text", "text": "servo 是"},
+            {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}},
+            {"type": "text", "text": "一个浏览器引擎。"}
+        ]}}]});
+        assert_eq!(extract_content(&resp), "servo 是一个浏览器引擎。");
+    }
+}
@@ -0,0 +1,443 @@
+use async_trait::async_trait;
+use super::format;
+use super::types::{Platform, PlatformOptions, RepoContent};
+use crate::http;
+use crate::repo::{DemoMedia, Repo};
+use log::warn;
+use anyhow::{anyhow, Context, Result};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+// Bluesky 限制每条帖子最多 300 个字素
+const MAX_LENGTH: usize = 300;
+const DEFAULT_TEMPLATE: &str = "{content}\n\n{url}";
+// Bluesky 图片最大 1MB
+const MAX_IMAGE_SIZE: usize = 1_000_000;
+
+fn default_pds_url() -> String {
+    "https://bsky.social".to_string()
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Bluesky {
+    handle: String,
+    app_password: String,
+    #[serde(default = "default_pds_url")]
+    pds_url: String,
+    #[serde(flatten)]
+    options: PlatformOptions,
+    template: Option<String>,
+    // 登录后的会话，多次推送之间复用
+    #[serde(skip)]
+    session: Arc<RwLock<Option<Session>>>,
+}
+
+#[async_trait]
+impl Platform for Bluesky {
+    fn name(&self) -> &str {
+        "bluesky"
+    }
+
+    fn options(&self) -> &PlatformOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut PlatformOptions {
+        &mut self.options
+    }
+
+    async fn post(&self, content: &str) -> Result<Option<String>> {
+        self.with_session(|client, session| async move { self.create_post(&client, &session, content, None).await })
+            .await
+    }
+
+    async fn post_with_media(&self, content: &str, media: &DemoMedia) -> Result<Option<String>> {
+        if media.data.len() > MAX_IMAGE_SIZE {
+            warn!("demo media {} is too large for bluesky, posting text only", media.url);
+            return self.post(content).await;
+        }
+        self.with_session(|client, session| async move {
+            let resp = client
+                .post(format!("{}/xrpc/com.atproto.repo.uploadBlob", self.pds_url))
+                .bearer_auth(&session.access_jwt)
+                .header(reqwest::header::CONTENT_TYPE, &media.mime_type)
+                .body(media.data.clone())
+                .send()
+                .await?;
+            let resp: Value = check(resp).await.context("While uploading bluesky blob")?.json().await?;
+            let embed = json!({
+                "$type": "app.bsky.embed.images",
+                "images": [{"alt": "", "image": resp["blob"]}],
+            });
+            self.create_post(&client, &session, content, Some(embed)).await
+        })
+        .await
+    }
+
+    // 通过个人资料中的 pinnedPost 字段置顶
+    async fn pin(&self, message_id: &str) -> Result<()> {
+        self.with_session(|client, session| async move {
+            let resp = client
+                .get(format!("{}/xrpc/com.atproto.repo.getRecord", self.pds_url))
+                .bearer_auth(&session.access_jwt)
+                .query(&[
+                    ("repo", session.did.as_str()),
+                    ("collection", "app.bsky.feed.post"),
+                    ("rkey", record_key(message_id)),
+                ])
+                .send()
+                .await?;
+            let post: Value = check(resp).await.context("While getting bluesky post")?.json().await?;
+            let mut profile = self.get_profile(&client, &session).await?;
+            profile["pinnedPost"] = json!({"uri": message_id, "cid": post["cid"]});
+            self.put_profile(&client, &session, profile).await
+        })
+        .await
+    }
+
+    async fn unpin(&self, message_id: &str) -> Result<()> {
+        self.with_session(|client, session| async move {
+            let mut profile = self.get_profile(&client, &session).await?;
+            // 置顶已被手动更换时不做处理
+            if profile["pinnedPost"]["uri"].as_str() != Some(message_id) {
+                return Ok(());
+            }
+            if let Some(record) = profile.as_object_mut() {
+                record.remove("pinnedPost");
+            }
+            self.put_profile(&client, &session, profile).await
+        })
+        .await
+    }
+
+    async fn delete(&self, message_id: &str) -> Result<()> {
+        self.with_session(|client, session| async move {
+            let resp = client
+                .post(format!("{}/xrpc/com.atproto.repo.deleteRecord", self.pds_url))
+                .bearer_auth(&session.access_jwt)
+                .json(&json!({
+                    "repo": session.did,
+                    "collection": "app.bsky.feed.post",
+                    "rkey": record_key(message_id),
+                }))
+                .send()
+                .await?;
+            check(resp).await.context("While deleting bluesky post")?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent> {
+        let mut template = format::repo_template(&self.options, repo, self.template.as_deref(), DEFAULT_TEMPLATE);
+        if self.options.source_language_tag {
+            if let Some(language) = format::source_language_name(repo) {
+                template = format!("#{} {}", language, template);
+            }
+        }
+        let render = |content: &str| format::render_repo(&template, &self.options, repo, &[], content);
+        let max_length = self.options.max_length(MAX_LENGTH);
+        let length_left = max_length.saturating_sub(format::grapheme_count(&render("")));
+        let content = repo.get_content(&self.options, length_left, format::grapheme_count).await.context("While getting repo content")?;
+        Ok(RepoContent {
+            content: format::fit_content(render, &content.content, max_length, format::grapheme_count),
+            summary: content.content,
+            token_usage: content.total_tokens,
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Session {
+    access_jwt: String,
+    refresh_jwt: String,
+    did: String,
+}
+
+impl Session {
+    fn parse(resp: &Value) -> Result<Self> {
+        let field = |name: &str| {
+            resp[name]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| anyhow!("bluesky session without {}: {}", name, resp))
+        };
+        Ok(Session {
+            access_jwt: field("accessJwt")?,
+            refresh_jwt: field("refreshJwt")?,
+            did: field("did")?,
+        })
+    }
+}
+
+// access token 过期，需要刷新会话
+#[derive(Debug)]
+struct ExpiredToken;
+
+impl fmt::Display for ExpiredToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bluesky access token expired")
+    }
+}
+
+impl std::error::Error for ExpiredToken {}
+
+impl Bluesky {
+    // 会话在多次推送之间复用，access token 过期时刷新会话后重试一次
+    async fn with_session<T, F, Fut>(&self, f: F) -> Result<T>
+    where
+        F: Fn(ClientWithMiddleware, Session) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let client = client();
+        let cached = self.session.read().unwrap().clone();
+        let session = match cached {
+            Some(session) => session,
+            None => self.create_session(&client).await?,
+        };
+        match f(client.clone(), session.clone()).await {
+            Err(e) if e.is::<ExpiredToken>() => {
+                let session = self.refresh_session(&client, &session).await?;
+                f(client, session).await
+            }
+            result => result,
+        }
+    }
+
+    async fn create_session(&self, client: &ClientWithMiddleware) -> Result<Session> {
+        let resp = client
+            .post(format!("{}/xrpc/com.atproto.server.createSession", self.pds_url))
+            .json(&json!({
+                "identifier": self.handle,
+                "password": self.app_password,
+            }))
+            .send()
+            .await?;
+        let resp: Value = check(resp).await.context("While creating bluesky session")?.json().await?;
+        let session = Session::parse(&resp)?;
+        *self.session.write().unwrap() = Some(session.clone());
+        Ok(session)
+    }
+
+    // refresh token 也过期时重新登录
+    async fn refresh_session(&self, client: &ClientWithMiddleware, session: &Session) -> Result<Session> {
+        let resp = client
+            .post(format!("{}/xrpc/com.atproto.server.refreshSession", self.pds_url))
+            .bearer_auth(&session.refresh_jwt)
+            .send()
+            .await?;
+        let refreshed = match check(resp).await {
+            Ok(resp) => Session::parse(&resp.json().await?)?,
+            Err(e) => {
+                warn!("refresh bluesky session failed, logging in again: {:#}", e);
+                return self.create_session(client).await;
+            }
+        };
+        *self.session.write().unwrap() = Some(refreshed.clone());
+        Ok(refreshed)
+    }
+
+    async fn create_post(
+        &self,
+        client: &ClientWithMiddleware,
+        session: &Session,
+        content: &str,
+        embed: Option<Value>,
+    ) -> Result<Option<String>> {
+        let mut record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": content,
+            "facets": facets(content),
+            "createdAt": OffsetDateTime::now_utc().format(&Rfc3339)?,
+        });
+        if let Some(embed) = embed {
+            record["embed"] = embed;
+        }
+        let data = json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": record,
+        });
+        let resp = client
+            .post(format!("{}/xrpc/com.atproto.repo.createRecord", self.pds_url))
+            .bearer_auth(&session.access_jwt)
+            .json(&data)
+            .send()
+            .await?;
+        let resp: Value = check(resp).await.context("While creating bluesky post")?.json().await?;
+        Ok(resp["uri"].as_str().map(String::from))
+    }
+
+    async fn get_profile(&self, client: &ClientWithMiddleware, session: &Session) -> Result<Value> {
+        let resp = client
+            .get(format!("{}/xrpc/com.atproto.repo.getRecord", self.pds_url))
+            .bearer_auth(&session.access_jwt)
+            .query(&[
+                ("repo", session.did.as_str()),
+                ("collection", "app.bsky.actor.profile"),
+                ("rkey", "self"),
+            ])
+            .send()
+            .await?;
+        let resp: Value = check(resp).await.context("While getting bluesky profile")?.json().await?;
+        Ok(resp["value"].clone())
+    }
+
+    async fn put_profile(&self, client: &ClientWithMiddleware, session: &Session, profile: Value) -> Result<()> {
+        let resp = client
+            .post(format!("{}/xrpc/com.atproto.repo.putRecord", self.pds_url))
+            .bearer_auth(&session.access_jwt)
+            .json(&json!({
+                "repo": session.did,
+                "collection": "app.bsky.actor.profile",
+                "rkey": "self",
+                "record": profile,
+            }))
+            .send()
+            .await?;
+        check(resp).await.context("While updating bluesky profile")?;
+        Ok(())
+    }
+}
+
+fn client() -> ClientWithMiddleware {
+    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+    ClientBuilder::new(http::client())
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+        .build()
+}
+
+// 请求失败时返回状态码和响应中的错误信息，access token 过期时返回 ExpiredToken
+async fn check(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body: Value = resp.json().await.unwrap_or_default();
+    if is_expired(status, &body) {
+        return Err(anyhow!(ExpiredToken));
+    }
+    Err(anyhow!("bluesky returned {}: {}", status, body))
+}
+
+fn is_expired(status: StatusCode, body: &Value) -> bool {
+    status == StatusCode::UNAUTHORIZED || body["error"] == "ExpiredToken"
+}
+
+// at://did/app.bsky.feed.post/rkey 中的 rkey
+fn record_key(uri: &str) -> &str {
+    uri.rsplit('/').next().unwrap_or(uri)
+}
+
+// 链接和话题标签需要通过 facet 声明，偏移量按 UTF-8 字节计算
+fn facets(text: &str) -> Vec<Value> {
+    let mut facets = link_facets(text);
+    facets.extend(tag_facets(text));
+    facets
+}
+
+// 链接在第一个不能出现在 URL 中的字符处结束，例如 wrap_url 的 >、括号、中文标点和文字
+fn is_url_char(c: char) -> bool {
+    c.is_ascii_graphic() && !matches!(c, '<' | '>' | '(' | ')' | '"' | '\'' | '`')
+}
+
+// byteStart / byteEnd 为 UTF-8 字节偏移
+fn link_facets(text: &str) -> Vec<Value> {
+    let mut facets = vec![];
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("https://").map(|i| i + offset) {
+        let end = text[start..]
+            .find(|c| !is_url_char(c))
+            .map(|i| i + start)
+            .unwrap_or(text.len());
+        facets.push(json!({
+            "index": {"byteStart": start, "byteEnd": end},
+            "features": [{
+                "$type": "app.bsky.richtext.facet#link",
+                "uri": &text[start..end],
+            }]
+        }));
+        offset = end;
+    }
+    facets
+}
+
+// 位于开头或空白之后的 #tag
+fn tag_facets(text: &str) -> Vec<Value> {
+    let mut facets = vec![];
+    for (start, _) in text.match_indices('#') {
+        if text[..start].chars().last().is_some_and(|c| !c.is_whitespace()) {
+            continue;
+        }
+        let end = text[start..]
+            .find(char::is_whitespace)
+            .map(|i| i + start)
+            .unwrap_or(text.len());
+        if end - start <= 1 {
+            continue;
+        }
+        facets.push(json!({
+            "index": {"byteStart": start, "byteEnd": end},
+            "features": [{
+                "$type": "app.bsky.richtext.facet#tag",
+                "tag": &text[start + 1..end],
+            }]
+        }));
+    }
+    facets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{facets, is_expired, link_facets};
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn test_is_expired() {
+        assert!(is_expired(StatusCode::BAD_REQUEST, &json!({"error": "ExpiredToken", "message": "Token has expired"})));
+        assert!(is_expired(StatusCode::UNAUTHORIZED, &json!({})));
+        assert!(!is_expired(StatusCode::BAD_REQUEST, &json!({"error": "InvalidRequest"})));
+    }
+
+    fn link_ranges(text: &str) -> Vec<(usize, usize, String)> {
+        link_facets(text)
+            .iter()
+            .map(|facet| {
+                (
+                    facet["index"]["byteStart"].as_u64().unwrap() as usize,
+                    facet["index"]["byteEnd"].as_u64().unwrap() as usize,
+                    facet["features"][0]["uri"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_link_facets_with_cjk() {
+        let text = "浏览器引擎：https://github.com/servo/servo。很快";
+        let start = "浏览器引擎：".len();
+        let url = "https://github.com/servo/servo";
+        assert_eq!(link_ranges(text), vec![(start, start + url.len(), url.to_string())]);
+        assert_eq!(&text[start..start + url.len()], url);
+    }
+
+    #[test]
+    fn test_link_facets_with_wrapped_url() {
+        let text = "项目 <https://github.com/servo/servo?utm_source=bluesky> #Rust";
+        let url = "https://github.com/servo/servo?utm_source=bluesky";
+        let start = text.find(url).unwrap();
+        assert_eq!(link_ranges(text), vec![(start, start + url.len(), url.to_string())]);
+        assert_eq!(link_ranges("（https://a.dev/x）")[0].2, "https://a.dev/x");
+        assert_eq!(facets(text).len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use super::format;
+use super::types::{Platform, PlatformOptions, RepoContent};
+use crate::repo::Repo;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+const DEFAULT_TEMPLATE: &str = "{content}\n\n{url}";
+// 本地文件没有长度限制，只用于计算摘要长度
+const MAX_LENGTH: usize = 10000;
+const JSONL_FILE: &str = "posts.jsonl";
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    // 每条推送一个文本文件，文件名为推送时间
+    #[default]
+    Files,
+    // 追加到目录下的 posts.jsonl
+    Jsonl,
+}
+
+// 将推送内容写入本地目录，用于归档和调试模板
+#[derive(Deserialize, Clone)]
+pub struct File {
+    dir: String,
+    #[serde(default)]
+    format: FileFormat,
+    #[serde(flatten)]
+    options: PlatformOptions,
+    template: Option<String>,
+}
+
+#[async_trait]
+impl Platform for File {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn options(&self) -> &PlatformOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut PlatformOptions {
+        &mut self.options
+    }
+
+    // 返回写入的文件名，jsonl 格式没有消息 id
+    async fn post(&self, content: &str) -> Result<Option<String>> {
+        fs::create_dir_all(&self.dir).context(format!("While creating {}", self.dir))?;
+        let now = OffsetDateTime::now_utc();
+        match self.format {
+            FileFormat::Files => {
+                let name = format!(
+                    "{}-{:09}.txt",
+                    now.format(format_description!("[year][month][day]-[hour][minute][second]"))?,
+                    now.nanosecond()
+                );
+                let path = Path::new(&self.dir).join(&name);
+                fs::write(&path, content).context(format!("While writing {}", path.display()))?;
+                Ok(Some(name))
+            }
+            FileFormat::Jsonl => {
+                let path = Path::new(&self.dir).join(JSONL_FILE);
+                let line = json!({"ts": now.unix_timestamp(), "content": content});
+                let mut file = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .context(format!("While opening {}", path.display()))?;
+                writeln!(file, "{}", line).context(format!("While writing {}", path.display()))?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn edit(&self, message_id: &str, content: &str) -> Result<()> {
+        let path = Path::new(&self.dir).join(message_id);
+        fs::write(&path, content).context(format!("While writing {}", path.display()))
+    }
+
+    async fn delete(&self, message_id: &str) -> Result<()> {
+        let path = Path::new(&self.dir).join(message_id);
+        fs::remove_file(&path).context(format!("While removing {}", path.display()))
+    }
+
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent> {
+        let template = format::repo_template(&self.options, repo, self.template.as_deref(), DEFAULT_TEMPLATE);
+        let render = |content: &str| format::render_repo(&template, &self.options, repo, &[], content);
+        let max_length = self.options.max_length(MAX_LENGTH);
+        let length_left = max_length.saturating_sub(format::grapheme_count(&render("")));
+        let content = repo.get_content(&self.options, length_left, format::grapheme_count).await.context("While getting repo content")?;
+        Ok(RepoContent {
+            content: format::fit_content(render, &content.content, max_length, format::grapheme_count),
+            summary: content.content,
+            token_usage: content.total_tokens,
+        })
+    }
+}
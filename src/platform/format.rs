@@ -0,0 +1,409 @@
+use unicode_segmentation::UnicodeSegmentation;
+use super::types::{CardOptions, EmojiMode, PlatformOptions, RenderMode};
+use crate::developer::Developer;
+use crate::repo::{now_ts, Repo};
+
+pub const DEFAULT_DEVELOPER_TEMPLATE: &str =
+    "GitHub Trending 开发者：{name}（@{username}）\n\n热门项目：{repo_name}\n{repo_description}\n\n{url}";
+pub const DEFAULT_RETREND_TEMPLATE: &str = "再次上榜：{stars} stars，今日 +{stars_today}\n{url}";
+
+// 将模板中的 {key} 占位符替换为对应的值，只扫描一遍模板，替换进来的描述、简介中的 {url} 等不会再被替换
+// 同名的占位符使用第一个值，未知的占位符原样保留
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let key = &after[..end];
+            let is_key = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+            let value = vars.iter().find(|(name, _)| is_key && *name == key)?.1;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                result.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+// 渲染完整消息，超出 max_length 时只截短 {content}，保证链接、标签和页脚完整
+// measure 为平台计算长度的方式（字节数或字素数）
+pub fn fit_content<R, M>(render: R, content: &str, max_length: usize, measure: M) -> String
+where
+    R: Fn(&str) -> String,
+    M: Fn(&str) -> usize,
+{
+    let rendered = render(content);
+    if measure(&rendered) <= max_length {
+        return rendered;
+    }
+    let graphemes: Vec<&str> = content.graphemes(true).collect();
+    let truncated = |keep: usize| {
+        if keep == 0 {
+            render("")
+        } else {
+            render(&format!("{} ...", graphemes[..keep].concat()))
+        }
+    };
+    // 二分查找能放下的最多字素数
+    let (mut low, mut high) = (0, graphemes.len().saturating_sub(1));
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if measure(&truncated(mid)) <= max_length {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    truncated(low)
+}
+
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+// 来源语言的显示名称，页面上的语言名与之一致时使用页面上的写法（例如 JavaScript）
+pub fn source_language_name(repo: &Repo) -> Option<String> {
+    let slug = repo.source_language.as_str();
+    if slug.is_empty() {
+        return None;
+    }
+    if repo.language.eq_ignore_ascii_case(slug) {
+        return Some(repo.language.replace(' ', ""));
+    }
+    let mut chars = slug.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+// 按平台配置处理 {url}：强制 https、追加跟踪参数、用尖括号包裹以禁止链接预览
+pub fn format_url(url: &str, options: &PlatformOptions) -> String {
+    let mut url = url.to_string();
+    if options.force_https {
+        if let Some(rest) = url.strip_prefix("http://") {
+            url = format!("https://{}", rest);
+        }
+    }
+    if let Some(params) = options.url_params.as_deref().filter(|params| !params.is_empty()) {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url = format!("{}{}{}", url, separator, params.trim_start_matches(['?', '&']));
+    }
+    if options.wrap_url {
+        url = format!("<{}>", url);
+    }
+    url
+}
+
+// 按 render_mode 选择平台的模板或内置的卡片格式
+pub fn repo_template(options: &PlatformOptions, repo: &Repo, template: Option<&str>, default: &str) -> String {
+    match options.render_mode {
+        RenderMode::Template => template.unwrap_or(default).to_string(),
+        RenderMode::Card => card_template(&options.card, repo),
+    }
+}
+
+// 卡片由标题行、分隔线、简介和页脚组成，页脚中没有值的项目不显示
+fn card_template(card: &CardOptions, repo: &Repo) -> String {
+    let border = card.border.repeat(card.width);
+    let mut footer = vec![if repo.stars_today > 0 {
+        format!("{}{{stars}}（+{{stars_today}}）", card.stars_prefix)
+    } else {
+        format!("{}{{stars}}", card.stars_prefix)
+    }];
+    if !repo.language.is_empty() {
+        footer.push("{language}".to_string());
+    }
+    footer.push("{url}".to_string());
+    format!(
+        "{}{{author}}/{{name}}\n{}\n{{content}}\n{}\n{}",
+        card.title_prefix,
+        border,
+        border,
+        footer.join(&card.separator)
+    )
+}
+
+// 渲染项目相关的通用占位符，vars 为平台自定义的占位符（如 {tags}），{content} 最后替换
+pub fn render_repo(
+    template: &str,
+    options: &PlatformOptions,
+    repo: &Repo,
+    vars: &[(&str, &str)],
+    content: &str,
+) -> String {
+    render_repo_escaped(template, options, repo, vars, content, str::to_string)
+}
+
+// 同 render_repo，但占位符的值先经过 escape 转义，模板本身的格式标记保持不变
+pub fn render_repo_escaped(
+    template: &str,
+    options: &PlatformOptions,
+    repo: &Repo,
+    vars: &[(&str, &str)],
+    content: &str,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let content = normalize_emoji(content, options.emoji);
+    let url = format_url(&repo.get_url(), options);
+    let rank = repo.rank.to_string();
+    let number = |n: u64| options.locale.format_number(n as i64, options.compact_numbers);
+    let stars = number(repo.stars);
+    let stars_today = number(repo.stars_today);
+    let stars_delta = options.locale.format_number(repo.stars_delta, options.compact_numbers);
+    let open_issues = repo.open_issues.map(number).unwrap_or_default();
+    let open_prs = repo.open_prs.map(number).unwrap_or_default();
+    let trending_context = repo.trending_context();
+    let topics = repo.topics.join(", ");
+    let author_followers = repo.author_followers.map(number).unwrap_or_default();
+    let updated = repo
+        .pushed_at
+        .map(|ts| options.locale.format_relative(now_ts().saturating_sub(ts)))
+        .unwrap_or_default();
+    let badges = if options.badges {
+        badge_urls(repo).join("\n")
+    } else {
+        String::new()
+    };
+    let mut all = vars.to_vec();
+    all.extend([
+        ("url", url.as_str()),
+        ("rank", rank.as_str()),
+        ("stars", stars.as_str()),
+        ("stars_today", stars_today.as_str()),
+        ("stars_delta", stars_delta.as_str()),
+        ("open_issues", open_issues.as_str()),
+        ("open_prs", open_prs.as_str()),
+        ("language", repo.language.as_str()),
+        ("source_language", repo.source_language.as_str()),
+        ("trending_context", trending_context.as_str()),
+        ("topics", topics.as_str()),
+        ("updated", updated.as_str()),
+        ("latest_release", repo.latest_release.as_str()),
+        ("release_date", repo.release_date.as_str()),
+        ("why_trending", repo.why_trending.as_str()),
+        ("badges", badges.as_str()),
+        ("author", repo.author.as_str()),
+        ("author_type", repo.author_type.as_str()),
+        ("author_followers", author_followers.as_str()),
+        ("name", repo.name.as_str()),
+        // 未翻译时使用原名
+        ("translated_name", if repo.translated_name.is_empty() { &repo.name } else { &repo.translated_name }),
+        ("description", repo.description.as_str()),
+        (
+            "translated_description",
+            repo.translated_description.as_deref().unwrap_or(&repo.description),
+        ),
+        ("content", content.as_str()),
+    ]);
+    let escaped: Vec<(&str, String)> = all.iter().map(|(key, value)| (*key, escape(value))).collect();
+    let all: Vec<(&str, &str)> = escaped.iter().map(|(key, value)| (*key, value.as_str())).collect();
+    let rendered = render(template, &all);
+    match &options.post_footer {
+        Some(footer) if !footer.is_empty() => format!("{}\n\n{}", rendered, render(footer, &all)),
+        _ => rendered,
+    }
+}
+
+// 再次上榜时在原消息下回复的内容，不附带 post_footer
+pub fn render_retrend(options: &PlatformOptions, repo: &Repo) -> String {
+    let template = options.retrend_template.as_deref().unwrap_or(DEFAULT_RETREND_TEMPLATE);
+    let options = PlatformOptions {
+        post_footer: None,
+        ..options.clone()
+    };
+    render_repo(template, &options, repo, &[], "")
+}
+
+// shields.io 徽章图片地址，stars 和 license 由 shields.io 实时查询
+pub fn badge_urls(repo: &Repo) -> Vec<String> {
+    let mut urls = vec![format!(
+        "https://img.shields.io/github/stars/{}/{}",
+        repo.author, repo.name
+    )];
+    if !repo.language.is_empty() {
+        // shields.io 静态徽章中 - 需要转义为 --
+        let language: String = url::form_urlencoded::byte_serialize(repo.language.replace('-', "--").as_bytes()).collect();
+        urls.push(format!("https://img.shields.io/badge/language-{}-blue", language));
+    }
+    urls.push(format!(
+        "https://img.shields.io/github/license/{}/{}",
+        repo.author, repo.name
+    ));
+    urls
+}
+
+pub fn render_developer(template: &str, options: &PlatformOptions, developer: &Developer) -> String {
+    render(template, &[
+        ("url", &format_url(&developer.get_url(), options)),
+        ("username", &developer.username),
+        ("name", &developer.name),
+        ("repo_name", &developer.repo_name),
+        ("repo_description", &developer.repo_description),
+    ])
+}
+
+pub fn normalize_emoji(content: &str, mode: EmojiMode) -> String {
+    match mode {
+        EmojiMode::Keep => content.to_string(),
+        EmojiMode::Strip => replace_shortcodes(content, |_| Some(""))
+            .graphemes(true)
+            .filter(|g| emojis::get(g).is_none())
+            .collect(),
+        EmojiMode::Unicode => replace_shortcodes(content, |emoji| Some(emoji.as_str())),
+    }
+}
+
+// 替换 :shortcode: 形式的 emoji，无法识别的保持原样
+fn replace_shortcodes<F>(content: &str, f: F) -> String
+where
+    F: Fn(&'static emojis::Emoji) -> Option<&'static str>,
+{
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find(':') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let replaced = after.find(':').and_then(|end| {
+            let code = &after[..end];
+            if code.is_empty() || code.contains(char::is_whitespace) {
+                return None;
+            }
+            let replacement = emojis::get_by_shortcode(code).and_then(&f)?;
+            Some((replacement, end))
+        });
+        match replaced {
+            Some((replacement, end)) => {
+                result.push_str(replacement);
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push(':');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit_content, grapheme_count, render, render_repo, render_retrend, repo_template};
+    use crate::platform::types::{PlatformOptions, RenderMode};
+    use crate::repo::Repo;
+
+    #[test]
+    fn test_render_card() {
+        let options = PlatformOptions {
+            render_mode: RenderMode::Card,
+            ..Default::default()
+        };
+        let repo = Repo {
+            author: "servo".to_string(),
+            name: "servo".to_string(),
+            language: "Rust".to_string(),
+            stars: 30000,
+            stars_today: 420,
+            ..Default::default()
+        };
+        let template = repo_template(&options, &repo, Some("{content}"), "{content}");
+        assert_eq!(
+            render_repo(&template, &options, &repo, &[], "一个浏览器引擎。"),
+            "📦 servo/servo\n━━━━━━━━━━━━━━━━\n一个浏览器引擎。\n━━━━━━━━━━━━━━━━\n\
+             ⭐ 30,000（+420） · Rust · https://github.com/servo/servo"
+        );
+
+        let repo = Repo { language: String::new(), stars_today: 0, ..repo };
+        let template = repo_template(&options, &repo, None, "{content}");
+        assert!(template.ends_with("\n⭐ {stars} · {url}"));
+    }
+
+    #[test]
+    fn test_render_does_not_expand_values() {
+        assert_eq!(
+            render("{content} {url} {unknown} {", &[("url", "https://a.dev"), ("content", "see {url}")]),
+            "see {url} https://a.dev {unknown} {"
+        );
+    }
+
+    #[test]
+    fn test_render_retrend_without_footer() {
+        let options = PlatformOptions {
+            post_footer: Some("订阅：https://example.com".to_string()),
+            ..Default::default()
+        };
+        let repo = Repo {
+            author: "servo".to_string(),
+            name: "servo".to_string(),
+            stars: 30000,
+            stars_today: 420,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_retrend(&options, &repo),
+            "再次上榜：30,000 stars，今日 +420\nhttps://github.com/servo/servo"
+        );
+    }
+
+    #[test]
+    fn test_render_translated_name() {
+        let options = PlatformOptions::default();
+        let repo = Repo {
+            name: "servo".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(render_repo("{name}（{translated_name}）", &options, &repo, &[], ""), "servo（servo）");
+        let repo = Repo { translated_name: "伺服".to_string(), ..repo };
+        assert_eq!(render_repo("{name}（{translated_name}）", &options, &repo, &[], ""), "servo（伺服）");
+    }
+
+    fn render_post(content: &str) -> String {
+        render("{content}\n\nhttps://github.com/a/b\n\n#tag", &[("content", content)])
+    }
+
+    #[test]
+    fn test_fit_content_at_limit() {
+        let full = render_post("abcdefghij");
+        let fitted = fit_content(render_post, "abcdefghij", full.len(), str::len);
+        assert_eq!(fitted, full);
+    }
+
+    #[test]
+    fn test_fit_content_one_over_limit() {
+        let full = render_post("abcdefghij");
+        let fitted = fit_content(render_post, "abcdefghij", full.len() - 1, str::len);
+        assert_eq!(fitted.len(), full.len() - 1);
+        assert!(fitted.starts_with("abcde ..."));
+        assert!(fitted.ends_with("https://github.com/a/b\n\n#tag"));
+    }
+
+    #[test]
+    fn test_fit_content_keeps_graphemes() {
+        let content = "中文摘要👨‍👩‍👧内容更多更多";
+        let max_length = render_post("").len() + 20;
+        let fitted = fit_content(render_post, content, max_length, str::len);
+        assert!(fitted.len() <= max_length);
+        assert!(fitted.starts_with("中文摘要 ..."));
+
+        let max_length = grapheme_count(&render_post("")) + 9;
+        let fitted = fit_content(render_post, content, max_length, grapheme_count);
+        assert_eq!(grapheme_count(&fitted), max_length);
+        assert!(fitted.starts_with("中文摘要👨‍👩‍👧 ..."));
+    }
+
+    #[test]
+    fn test_fit_content_overhead_exceeds_limit() {
+        let fitted = fit_content(render_post, "abc", 5, str::len);
+        assert_eq!(fitted, render_post(""));
+    }
+}
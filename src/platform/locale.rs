@@ -0,0 +1,158 @@
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+// 模板中数字和相对时间的格式，按 locale 的语言部分匹配，例如 zh-CN、zh_TW 都使用中文
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(try_from = "String")]
+pub enum Locale {
+    // 1,234，2 days ago
+    #[default]
+    En,
+    // 1,234，2天前
+    Zh,
+    // 1,234，2日前
+    Ja,
+    // 1.234，vor 2 Tagen
+    De,
+    // 1 234，il y a 2 jours
+    Fr,
+}
+
+impl TryFrom<String> for Locale {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let language = value.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+        match language.as_str() {
+            "en" => Ok(Locale::En),
+            "zh" => Ok(Locale::Zh),
+            "ja" => Ok(Locale::Ja),
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            _ => Err(format!("unsupported locale {}, expected one of en, zh, ja, de, fr", value)),
+        }
+    }
+}
+
+impl Locale {
+    fn group_separator(self) -> &'static str {
+        match self {
+            Locale::De => ".",
+            Locale::Fr => " ",
+            _ => ",",
+        }
+    }
+
+    fn decimal_separator(self) -> &'static str {
+        match self {
+            Locale::De | Locale::Fr => ",",
+            _ => ".",
+        }
+    }
+
+    // 按千位分组，compact 时缩写为 1.2k / 1.2万
+    pub fn format_number(self, n: i64, compact: bool) -> String {
+        let sign = if n < 0 { "-" } else { "" };
+        let abs = n.unsigned_abs();
+        let formatted = match self.compact_unit(abs) {
+            Some((divisor, suffix)) if compact => {
+                let scaled = format!("{:.1}", abs as f64 / divisor as f64);
+                let scaled = scaled.strip_suffix(".0").unwrap_or(&scaled).replace('.', self.decimal_separator());
+                format!("{}{}", scaled, suffix)
+            }
+            _ => self.group(abs),
+        };
+        format!("{}{}", sign, formatted)
+    }
+
+    fn compact_unit(self, n: u64) -> Option<(u64, &'static str)> {
+        let units: &[(u64, &str)] = match self {
+            Locale::Zh => &[(100_000_000, "亿"), (10_000, "万")],
+            Locale::Ja => &[(100_000_000, "億"), (10_000, "万")],
+            _ => &[(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "k")],
+        };
+        units.iter().copied().find(|(divisor, _)| n >= *divisor)
+    }
+
+    fn group(self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut groups = vec![];
+        let mut end = digits.len();
+        while end > 3 {
+            groups.push(&digits[end - 3..end]);
+            end -= 3;
+        }
+        groups.push(&digits[..end]);
+        groups.reverse();
+        groups.join(self.group_separator())
+    }
+
+    // 将 seconds 秒之前渲染为“2天前”这样的相对时间，月按 30 天、年按 365 天计算
+    pub fn format_relative(self, seconds: u64) -> String {
+        const UNITS: [(u64, usize); 5] = [(365 * 86400, 4), (30 * 86400, 3), (86400, 2), (3600, 1), (60, 0)];
+        let Some((n, unit)) = UNITS
+            .iter()
+            .find(|(secs, _)| seconds >= *secs)
+            .map(|(secs, unit)| (seconds / secs, *unit))
+        else {
+            return match self {
+                Locale::En => "just now",
+                Locale::Zh => "刚刚",
+                Locale::Ja => "たった今",
+                Locale::De => "gerade eben",
+                Locale::Fr => "à l'instant",
+            }
+            .to_string();
+        };
+        match self {
+            Locale::En => {
+                let name = ["minute", "hour", "day", "month", "year"][unit];
+                format!("{} {}{} ago", n, name, if n == 1 { "" } else { "s" })
+            }
+            Locale::Zh => format!("{}{}前", n, ["分钟", "小时", "天", "个月", "年"][unit]),
+            Locale::Ja => format!("{}{}前", n, ["分", "時間", "日", "か月", "年"][unit]),
+            Locale::De => {
+                let (one, many) = [("Minute", "Minuten"), ("Stunde", "Stunden"), ("Tag", "Tagen"), ("Monat", "Monaten"), ("Jahr", "Jahren")][unit];
+                format!("vor {} {}", n, if n == 1 { one } else { many })
+            }
+            Locale::Fr => {
+                let name = ["minute", "heure", "jour", "mois", "an"][unit];
+                let plural = if n > 1 && name != "mois" { "s" } else { "" };
+                format!("il y a {} {}{}", n, name, plural)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(Locale::En.format_number(1234567, false), "1,234,567");
+        assert_eq!(Locale::De.format_number(1234, false), "1.234");
+        assert_eq!(Locale::Fr.format_number(-1234, false), "-1 234");
+        assert_eq!(Locale::En.format_number(999, true), "999");
+        assert_eq!(Locale::En.format_number(1234, true), "1.2k");
+        assert_eq!(Locale::De.format_number(2_000_000, true), "2M");
+        assert_eq!(Locale::Zh.format_number(9999, true), "9,999");
+        assert_eq!(Locale::Zh.format_number(12345, true), "1.2万");
+    }
+
+    #[test]
+    fn test_format_relative() {
+        assert_eq!(Locale::Zh.format_relative(2 * 86400 + 10), "2天前");
+        assert_eq!(Locale::En.format_relative(3600), "1 hour ago");
+        assert_eq!(Locale::En.format_relative(30), "just now");
+        assert_eq!(Locale::De.format_relative(3 * 30 * 86400), "vor 3 Monaten");
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(Locale::try_from("zh_TW".to_string()), Ok(Locale::Zh));
+        assert_eq!(Locale::try_from("en-US".to_string()), Ok(Locale::En));
+        assert!(Locale::try_from("xx".to_string()).is_err());
+    }
+}
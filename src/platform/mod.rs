@@ -1,2 +1,8 @@
+pub mod bluesky;
+pub mod file;
+pub mod format;
+pub mod locale;
+pub mod nats;
+pub mod telegram;
 pub mod zsxq;
 pub mod types;
@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use super::format;
+use super::types::{Platform, PlatformOptions, RepoContent};
+use crate::repo::{now_ts, Repo};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+// 消息本身没有长度限制，只用于计算摘要长度
+const MAX_LENGTH: usize = 2000;
+
+// 将项目信息和 AI 简介以 JSON 消息发布到 NATS，由下游服务负责投递
+#[derive(Deserialize, Clone)]
+pub struct Nats {
+    // 例如 nats://localhost:4222
+    url: String,
+    subject: String,
+    #[serde(flatten)]
+    options: PlatformOptions,
+}
+
+#[async_trait]
+impl Platform for Nats {
+    fn name(&self) -> &str {
+        "nats"
+    }
+
+    fn options(&self) -> &PlatformOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut PlatformOptions {
+        &mut self.options
+    }
+
+    // content_by_repo 生成的 JSON 原样发布，告警等其他内容按文本发布
+    async fn post(&self, content: &str) -> Result<Option<String>> {
+        let client = async_nats::connect(self.url.as_str())
+            .await
+            .context(format!("While connecting {}", self.url))?;
+        client
+            .publish(self.subject.clone(), content.to_string().into())
+            .await
+            .context(format!("While publishing to {}", self.subject))?;
+        client.flush().await.context("While flushing nats client")?;
+        Ok(None)
+    }
+
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent> {
+        let summary = repo
+            .get_content(&self.options, self.options.max_length(MAX_LENGTH), format::grapheme_count)
+            .await
+            .context("While getting repo content")?;
+        let message = json!({
+            "id": repo.id(),
+            "url": format::format_url(&repo.get_url(), &self.options),
+            "author": repo.author,
+            "name": repo.name,
+            "description": repo.description,
+            "translated_description": repo.translated_description,
+            "language": repo.language,
+            "source_language": repo.source_language,
+            "rank": repo.rank,
+            "stars": repo.stars,
+            "stars_today": repo.stars_today,
+            "topics": repo.topics,
+            "summary": summary.content,
+            "ts": now_ts(),
+        });
+        Ok(RepoContent {
+            content: message.to_string(),
+            summary: summary.content,
+            token_usage: summary.total_tokens,
+        })
+    }
+}
@@ -0,0 +1,451 @@
+use async_trait::async_trait;
+use super::format;
+use super::types::{unsupported, Platform, PlatformOptions, RepoContent};
+use crate::http;
+use crate::repo::Repo;
+use log::error;
+use anyhow::{anyhow, Context, Result};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::RetryTransientMiddleware;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// Telegram 限制每条消息最多 4096 个字符（按 UTF-16 计算）
+const MAX_LENGTH: usize = 4096;
+const DEFAULT_TEMPLATE: &str = "{content}\n\n{url}";
+const DEFAULT_DISCUSSION_TEMPLATE: &str = "{content}\n\nStars：{stars}（今日 +{stars_today}）\n语言：{language}\n\n{url}";
+// MarkdownV2 中需要转义的字符
+const MARKDOWN_V2_SPECIAL: &str = "_*[]()~`>#+-=|{}.!\\";
+
+fn default_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Telegram {
+    bot_token: String,
+    #[serde(default = "default_api_url")]
+    api_url: String,
+    // 同一个机器人推送到多个聊天，每个聊天可以使用不同的 parse_mode
+    pub chats: Vec<TelegramChat>,
+    #[serde(flatten)]
+    options: PlatformOptions,
+    template: Option<String>,
+    // 讨论区回复的模板，{content} 为不受 summary_length 限制的完整简介
+    discussion_template: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TelegramChat {
+    // 数字 id 或 @channel_name
+    chat_id: String,
+    #[serde(default)]
+    parse_mode: ParseMode,
+    // 频道关联的讨论组，设置后在讨论组中回复频道消息，发送完整内容
+    discussion_chat_id: Option<String>,
+    // 覆盖平台的 template，可以包含该聊天 parse_mode 下的格式标记
+    template: Option<String>,
+}
+
+// 每个聊天的模板和 parse_mode 不同，项目内容按 chat_id 分别渲染后一起保存
+// 告警等其他内容是纯文本，发送时按聊天的 parse_mode 整体转义
+#[derive(Serialize, Deserialize, Default)]
+struct ChatMessages {
+    telegram_chats: HashMap<String, String>,
+}
+
+impl ChatMessages {
+    fn encode(&self) -> Result<String> {
+        serde_json::to_string(self).context("While encoding telegram messages")
+    }
+
+    fn decode(content: &str) -> Option<Self> {
+        if !content.starts_with("{\"telegram_chats\"") {
+            return None;
+        }
+        serde_json::from_str(content).ok()
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    // 纯文本，不解析格式
+    #[default]
+    #[serde(rename = "plain")]
+    Plain,
+    #[serde(rename = "HTML")]
+    Html,
+    MarkdownV2,
+}
+
+impl ParseMode {
+    fn name(self) -> Option<&'static str> {
+        match self {
+            ParseMode::Plain => None,
+            ParseMode::Html => Some("HTML"),
+            ParseMode::MarkdownV2 => Some("MarkdownV2"),
+        }
+    }
+
+    // 按聊天的 parse_mode 转义内容，避免简介中的符号被解析为格式
+    fn escape(self, text: &str) -> String {
+        match self {
+            ParseMode::Plain => text.to_string(),
+            ParseMode::Html => html_escape::encode_text(text).to_string(),
+            ParseMode::MarkdownV2 => text
+                .chars()
+                .flat_map(|c| {
+                    let escaped = MARKDOWN_V2_SPECIAL.contains(c).then_some('\\');
+                    escaped.into_iter().chain(std::iter::once(c))
+                })
+                .collect(),
+        }
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+#[async_trait]
+impl Platform for Telegram {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn options(&self) -> &PlatformOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut PlatformOptions {
+        &mut self.options
+    }
+
+    // 依次推送到所有聊天，只有全部失败时才返回错误，避免部分成功的内容下一轮重复推送
+    // 消息 id 为 chat_id:message_id，多个聊天用逗号分隔
+    async fn post(&self, content: &str) -> Result<Option<String>> {
+        let client = client();
+        let messages = ChatMessages::decode(content);
+        let mut message_ids = vec![];
+        let mut errors = vec![];
+        for chat in self.chats.iter() {
+            let text = match text_for(&messages, &chat.chat_id, chat.parse_mode, content) {
+                Ok(text) => text,
+                Err(e) => {
+                    errors.push(format!("{}: {:#}", chat.chat_id, e));
+                    continue;
+                }
+            };
+            let data = json!({
+                "chat_id": chat.chat_id,
+                "text": text,
+                "parse_mode": chat.parse_mode.name(),
+            });
+            match self.call(&client, "sendMessage", data).await {
+                Ok(resp) => match resp["message_id"].as_i64() {
+                    Some(id) => message_ids.push(format!("{}:{}", chat.chat_id, id)),
+                    None => errors.push(format!("{}: message_id not found in {}", chat.chat_id, resp)),
+                },
+                Err(e) => errors.push(format!("{}: {:#}", chat.chat_id, e)),
+            }
+        }
+        if message_ids.is_empty() {
+            return Err(anyhow!("post telegram failed: {}", errors.join("; ")));
+        }
+        for e in errors {
+            error!("post telegram chat failed, {}", e);
+        }
+        Ok(Some(message_ids.join(",")))
+    }
+
+    async fn pin(&self, message_id: &str) -> Result<()> {
+        self.call_each("pinChatMessage", message_id, json!({"disable_notification": true}))
+            .await
+            .context("While pinning telegram message")
+    }
+
+    async fn unpin(&self, message_id: &str) -> Result<()> {
+        self.call_each("unpinChatMessage", message_id, json!({}))
+            .await
+            .context("While unpinning telegram message")
+    }
+
+    async fn edit(&self, message_id: &str, content: &str) -> Result<()> {
+        let client = client();
+        let messages = ChatMessages::decode(content);
+        for (chat_id, id) in parse_message_ids(message_id) {
+            let parse_mode = self
+                .chats
+                .iter()
+                .find(|chat| chat.chat_id == chat_id)
+                .map_or(ParseMode::Plain, |chat| chat.parse_mode);
+            let data = json!({
+                "chat_id": chat_id,
+                "message_id": id,
+                "text": text_for(&messages, chat_id, parse_mode, content)?,
+                "parse_mode": parse_mode.name(),
+            });
+            self.call(&client, "editMessageText", data)
+                .await
+                .context(format!("While editing telegram message in {}", chat_id))?;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, message_id: &str) -> Result<()> {
+        self.call_each("deleteMessage", message_id, json!({}))
+            .await
+            .context("While deleting telegram message")
+    }
+
+    // 只有设置了 discussion_chat_id 的频道才能回复
+    fn supports_reply(&self) -> bool {
+        self.chats.iter().any(|chat| chat.discussion_chat_id.is_some())
+    }
+
+    async fn reply(&self, message_id: &str, content: &str) -> Result<()> {
+        let targets = self.discussion_targets(message_id);
+        if targets.is_empty() {
+            return Err(unsupported(self.name(), "discussion replies without discussion_chat_id"));
+        }
+        let client = client();
+        let messages = ChatMessages::decode(content);
+        for (chat, discussion_chat_id, id) in targets {
+            // 回复其他聊天中的消息需要在 reply_parameters 中指定原消息所在的聊天
+            let data = json!({
+                "chat_id": discussion_chat_id,
+                "text": text_for(&messages, &chat.chat_id, chat.parse_mode, content)?,
+                "parse_mode": chat.parse_mode.name(),
+                "reply_parameters": {
+                    "message_id": id.parse::<i64>().context(format!("invalid telegram message id {}", id))?,
+                    "chat_id": chat.chat_id,
+                    "allow_sending_without_reply": true,
+                },
+            });
+            self.call(&client, "sendMessage", data)
+                .await
+                .context(format!("While replying in {}", discussion_chat_id))?;
+        }
+        Ok(())
+    }
+
+    async fn discussion_content_by_repo(&self, repo: &Repo) -> Result<Option<String>> {
+        let chats: Vec<_> = self.chats.iter().filter(|chat| chat.discussion_chat_id.is_some()).collect();
+        if chats.is_empty() {
+            return Ok(None);
+        }
+        let options = PlatformOptions {
+            summary_length: None,
+            ..self.options.clone()
+        };
+        let template = self.discussion_template.as_deref().unwrap_or(DEFAULT_DISCUSSION_TEMPLATE);
+        let templates = chats.into_iter().map(|chat| (chat, template.to_string())).collect();
+        let rendered = self.render_chats(repo, &options, templates, MAX_LENGTH).await?;
+        Ok(Some(rendered.content))
+    }
+
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent> {
+        let templates = self
+            .chats
+            .iter()
+            .map(|chat| {
+                let template = chat.template.as_deref().or(self.template.as_deref());
+                (chat, format::repo_template(&self.options, repo, template, DEFAULT_TEMPLATE))
+            })
+            .collect();
+        self.render_chats(repo, &self.options, templates, self.options.max_length(MAX_LENGTH))
+            .await
+    }
+}
+
+impl Telegram {
+    // 简介只生成一次，长度按剩余空间最少的聊天计算，再按各聊天的模板和 parse_mode 渲染
+    async fn render_chats(
+        &self,
+        repo: &Repo,
+        options: &PlatformOptions,
+        templates: Vec<(&TelegramChat, String)>,
+        max_length: usize,
+    ) -> Result<RepoContent> {
+        let render = |chat: &TelegramChat, template: &str, content: &str| {
+            format::render_repo_escaped(template, options, repo, &[], content, |value| chat.parse_mode.escape(value))
+        };
+        let length_left = templates
+            .iter()
+            .map(|(chat, template)| max_length.saturating_sub(utf16_len(&render(chat, template, ""))))
+            .min()
+            .unwrap_or(max_length);
+        let content = repo.get_content(options, length_left, utf16_len).await.context("While getting repo content")?;
+        let mut messages = ChatMessages::default();
+        for (chat, template) in templates {
+            let text = format::fit_content(|content| render(chat, &template, content), &content.content, max_length, utf16_len);
+            messages.telegram_chats.insert(chat.chat_id.clone(), text);
+        }
+        Ok(RepoContent {
+            content: messages.encode()?,
+            summary: content.content,
+            token_usage: content.total_tokens,
+        })
+    }
+
+    // 请求地址中包含 bot token，错误信息中去掉地址，避免 token 出现在日志和告警中
+    async fn call(&self, client: &ClientWithMiddleware, method: &str, data: Value) -> Result<Value> {
+        let resp: Value = client
+            .post(format!("{}/bot{}/{}", self.api_url, self.bot_token, method))
+            .json(&data)
+            .send()
+            .await
+            .map_err(|e| match e {
+                reqwest_middleware::Error::Reqwest(e) => anyhow!(e.without_url()),
+                e => anyhow!(redact(&e.to_string(), &self.bot_token)),
+            })?
+            .json()
+            .await
+            .map_err(|e| e.without_url())?;
+        if resp["ok"].as_bool() != Some(true) {
+            return Err(anyhow!("telegram {} failed: {}", method, resp));
+        }
+        Ok(resp["result"].clone())
+    }
+
+    // 设置了讨论组的聊天及其消息 id
+    fn discussion_targets<'a>(&'a self, message_id: &'a str) -> Vec<(&'a TelegramChat, &'a str, &'a str)> {
+        parse_message_ids(message_id)
+            .into_iter()
+            .filter_map(|(chat_id, id)| {
+                let chat = self.chats.iter().find(|chat| chat.chat_id == chat_id)?;
+                Some((chat, chat.discussion_chat_id.as_deref()?, id))
+            })
+            .collect()
+    }
+
+    // 对 post 返回的每个聊天消息调用同一个接口
+    async fn call_each(&self, method: &str, message_id: &str, extra: Value) -> Result<()> {
+        let client = client();
+        for (chat_id, id) in parse_message_ids(message_id) {
+            let mut data = extra.clone();
+            data["chat_id"] = json!(chat_id);
+            data["message_id"] = json!(id);
+            self.call(&client, method, data).await?;
+        }
+        Ok(())
+    }
+}
+
+fn client() -> ClientWithMiddleware {
+    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
+    ClientBuilder::new(http::client())
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+        .build()
+}
+
+// 项目内容取该聊天渲染好的消息，其他内容按 parse_mode 整体转义
+fn text_for(messages: &Option<ChatMessages>, chat_id: &str, parse_mode: ParseMode, content: &str) -> Result<String> {
+    match messages {
+        Some(messages) => messages
+            .telegram_chats
+            .get(chat_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("no rendered message for chat {}", chat_id)),
+        None => Ok(parse_mode.escape(content)),
+    }
+}
+
+fn redact(text: &str, token: &str) -> String {
+    if token.is_empty() {
+        return text.to_string();
+    }
+    text.replace(token, "<redacted>")
+}
+
+// chat_id 可能以 @ 开头，但不会包含冒号
+fn parse_message_ids(message_id: &str) -> Vec<(&str, &str)> {
+    message_id
+        .split(',')
+        .filter_map(|pair| pair.rsplit_once(':'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_message_ids, redact, text_for, ChatMessages, ParseMode, Telegram};
+    use crate::platform::format::render_repo_escaped;
+    use crate::platform::types::PlatformOptions;
+    use crate::repo::Repo;
+    use serde_json::json;
+
+    #[test]
+    fn test_discussion_targets() {
+        let telegram: Telegram = serde_json::from_value(json!({
+            "bot_token": "token",
+            "chats": [
+                {"chat_id": "@channel", "discussion_chat_id": "@channel_chat"},
+                {"chat_id": "-100123"},
+            ],
+        }))
+        .unwrap();
+        let targets: Vec<_> = telegram
+            .discussion_targets("@channel:10,-100123:20")
+            .into_iter()
+            .map(|(chat, discussion, id)| (chat.chat_id.as_str(), discussion, id))
+            .collect();
+        assert_eq!(targets, vec![("@channel", "@channel_chat", "10")]);
+    }
+
+    #[test]
+    fn test_escape_per_parse_mode() {
+        let text = "Fast <web> framework (v1.0)!";
+        assert_eq!(ParseMode::Plain.escape(text), text);
+        assert_eq!(ParseMode::Html.escape(text), "Fast &lt;web&gt; framework (v1.0)!");
+        assert_eq!(ParseMode::MarkdownV2.escape(text), "Fast <web\\> framework \\(v1\\.0\\)\\!");
+    }
+
+    #[test]
+    fn test_escape_only_values() {
+        let repo = Repo {
+            author: "a".to_string(),
+            name: "b".to_string(),
+            ..Default::default()
+        };
+        let rendered = render_repo_escaped(
+            "<b>{name}</b>\n{content}",
+            &PlatformOptions::default(),
+            &repo,
+            &[],
+            "Fast <web> framework",
+            |value| ParseMode::Html.escape(value),
+        );
+        assert_eq!(rendered, "<b>b</b>\nFast &lt;web&gt; framework");
+    }
+
+    #[test]
+    fn test_text_for_chat() {
+        let mut messages = ChatMessages::default();
+        messages.telegram_chats.insert("@channel".to_string(), "<b>rendered</b>".to_string());
+        let encoded = messages.encode().unwrap();
+        let messages = ChatMessages::decode(&encoded);
+        assert_eq!(text_for(&messages, "@channel", ParseMode::Html, &encoded).unwrap(), "<b>rendered</b>");
+        assert!(text_for(&messages, "@other", ParseMode::Html, &encoded).is_err());
+        // 告警等纯文本整体转义
+        let messages = ChatMessages::decode("a < b");
+        assert_eq!(text_for(&messages, "@channel", ParseMode::Html, "a < b").unwrap(), "a &lt; b");
+    }
+
+    #[test]
+    fn test_redact_token() {
+        assert_eq!(
+            redact("error sending request for url (https://api.telegram.org/bot123:abc/sendMessage)", "123:abc"),
+            "error sending request for url (https://api.telegram.org/bot<redacted>/sendMessage)"
+        );
+    }
+
+    #[test]
+    fn test_parse_message_ids() {
+        assert_eq!(
+            parse_message_ids("-100123:45,@channel:67"),
+            vec![("-100123", "45"), ("@channel", "67")]
+        );
+    }
+}
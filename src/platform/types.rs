@@ -1,9 +1,268 @@
 use async_trait::async_trait;
-use crate::repo::Repo;
-use anyhow::Result;
+use super::format;
+use super::locale::Locale;
+use crate::developer::Developer;
+use crate::repo::{DemoMedia, Repo};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fmt;
+
+// 各平台通用的配置项
+#[derive(Deserialize, Clone, Default)]
+pub struct PlatformOptions {
+    // 平台单独的推送周期，未设置时使用 interval.post_ttl
+    pub post_ttl: Option<usize>,
+    // 同一平台两次推送的最小间隔（秒）
+    pub min_post_gap: Option<u64>,
+    // 摘要中 emoji 的处理方式，默认保持原样
+    #[serde(default)]
+    pub emoji: EmojiMode,
+    // 是否渲染 {badges} 占位符（shields.io 徽章图片地址），适合能展示图片的平台
+    #[serde(default)]
+    pub badges: bool,
+    // trending developers 模式下使用的模板
+    pub developer_template: Option<String>,
+    // 整条消息的最大长度，不能超过平台本身的限制
+    pub max_content_length: Option<usize>,
+    // 附带 README 中的演示图片或 GIF，仅对支持图片的平台生效
+    #[serde(default)]
+    pub attach_demo_media: bool,
+    // 双语模式，使用原文描述加中文翻译代替 AI 简介
+    #[serde(default)]
+    pub bilingual: bool,
+    // 有描述时 {content} 使用 README 简介还是翻译后的描述，描述为空时总是使用 README 简介
+    #[serde(default)]
+    pub primary_content: PrimaryContent,
+    // AI 简介的最小长度，过短时按 short_content 处理
+    pub min_content_length: Option<usize>,
+    #[serde(default)]
+    pub short_content: ShortContentAction,
+    // 读取到的 README 内容的最小长度（按字素计算），过短时按 thin_readme 处理，不再调用模型
+    pub min_readme_length: Option<usize>,
+    #[serde(default)]
+    pub thin_readme: ThinReadmeAction,
+    // 覆盖 openai.summary_prompt，不同平台可以使用不同风格的简介
+    pub summary_prompt: Option<String>,
+    // 简介的最大长度，同时会写进提示词
+    pub summary_length: Option<usize>,
+    // 要求模型返回 JSON 格式的结构化简介，按 summary_template 渲染为 {content}，解析失败时回退为普通文本
+    #[serde(default)]
+    pub structured_summary: bool,
+    // 结构化简介的模板，可用占位符：{title} {one_liner} {features}
+    pub summary_template: Option<String>,
+    // template 使用平台的 template 配置；card 使用内置的卡片格式，忽略 template
+    #[serde(default)]
+    pub render_mode: RenderMode,
+    #[serde(default)]
+    pub card: CardOptions,
+    // 追加在每条推送末尾的内容，例如订阅链接，未设置时使用全局的 post_footer
+    pub post_footer: Option<String>,
+    // 模板中数字和相对时间的格式，例如 zh-CN，默认 en-US
+    #[serde(default)]
+    pub locale: Locale,
+    // 数字缩写为 1.2k / 1.2万，只影响 {stars} 等数字占位符
+    #[serde(default)]
+    pub compact_numbers: bool,
+    // 是否自动添加来源语言的话题标签，例如 #Go
+    #[serde(default)]
+    pub source_language_tag: bool,
+    // 追加到 {url} 的查询参数，例如 utm_source=zsxq
+    pub url_params: Option<String>,
+    // 将 http:// 链接替换为 https://
+    #[serde(default)]
+    pub force_https: bool,
+    // 用尖括号包裹链接，Slack、Discord 等平台不会展开预览
+    #[serde(default)]
+    pub wrap_url: bool,
+    // 是否置顶每轮推送的第一条，并取消上一次的置顶
+    #[serde(default)]
+    pub pin_top: bool,
+    // 已推送的项目再次上榜且 star 明显增长时，修改原消息而不是跳过
+    #[serde(default)]
+    pub edit_on_update: bool,
+    // 触发修改的最小 star 增量
+    pub edit_min_star_delta: Option<u64>,
+    // 推送后该时间（秒）内再次上榜时，在原消息下回复最新数据而不是重新推送，需要平台支持回复
+    pub reply_on_retrend: Option<u64>,
+    // 再次上榜时回复的模板，可用占位符与 template 相同
+    pub retrend_template: Option<String>,
+    // 推送超过该时间（秒）后自动删除，适合只保留最近内容的频道
+    pub delete_after: Option<u64>,
+}
+
+pub const DEFAULT_EDIT_MIN_STAR_DELTA: u64 = 500;
+
+impl PlatformOptions {
+    pub fn edit_min_star_delta(&self) -> u64 {
+        self.edit_min_star_delta.unwrap_or(DEFAULT_EDIT_MIN_STAR_DELTA)
+    }
+
+    pub fn max_length(&self, platform_max: usize) -> usize {
+        self.max_content_length.map_or(platform_max, |max| max.min(platform_max))
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    #[default]
+    Template,
+    // 标题行、分隔线、简介和 stars / 语言 / 链接组成的卡片
+    Card,
+}
+
+// 卡片的装饰字符
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CardOptions {
+    // 分隔线使用的字符和重复次数
+    pub border: String,
+    pub width: usize,
+    // 标题行 author/name 前的符号
+    pub title_prefix: String,
+    // stars 前的符号
+    pub stars_prefix: String,
+    // 页脚各项之间的分隔符
+    pub separator: String,
+}
+
+impl Default for CardOptions {
+    fn default() -> Self {
+        CardOptions {
+            border: "━".to_string(),
+            width: 16,
+            title_prefix: "📦 ".to_string(),
+            stars_prefix: "⭐ ".to_string(),
+            separator: " · ".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimaryContent {
+    // 由模型阅读 README 生成简介
+    #[default]
+    Readme,
+    // 使用翻译后的项目描述，未配置 OpenAI 时使用原文
+    Description,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortContentAction {
+    // 要求模型写得更详细，重新生成一次
+    #[default]
+    Reprompt,
+    // 跳过该项目
+    Skip,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThinReadmeAction {
+    // 使用翻译后的项目描述，描述为空时跳过
+    #[default]
+    Description,
+    // 跳过该项目
+    Skip,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmojiMode {
+    #[default]
+    Keep,
+    // 移除所有 emoji 和 :shortcode:
+    Strip,
+    // 将 :rocket: 之类的 shortcode 转换为 unicode emoji
+    Unicode,
+}
+
+// 平台不支持的操作，例如编辑或删除消息
+#[derive(Debug)]
+pub struct Unsupported {
+    pub platform: String,
+    pub operation: &'static str,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} does not support {}", self.platform, self.operation)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+pub fn unsupported(platform: &str, operation: &'static str) -> anyhow::Error {
+    anyhow!(Unsupported {
+        platform: platform.to_string(),
+        operation,
+    })
+}
+
+// content_by_repo 的结果，summary 为生成的简介，不包含模板、链接和标签
+// token_usage 为生成简介时调用模型消耗的 token 数，记录在审计日志中
+pub struct RepoContent {
+    pub content: String,
+    pub summary: String,
+    pub token_usage: u64,
+}
 
 #[async_trait]
-pub trait Platform {
-    async fn post(&self, content: &str) -> Result<()>;
-    async fn content_by_repo(&self, repo: &Repo) -> Result<String>;
+pub trait Platform: Send + Sync {
+    fn name(&self) -> &str;
+    fn options(&self) -> &PlatformOptions;
+    fn options_mut(&mut self) -> &mut PlatformOptions;
+    // 推送成功后返回平台上的消息 id（如果平台提供）
+    async fn post(&self, content: &str) -> Result<Option<String>>;
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent>;
+
+    // 附带图片推送，不支持图片的平台只推送文字
+    async fn post_with_media(&self, content: &str, _media: &DemoMedia) -> Result<Option<String>> {
+        self.post(content).await
+    }
+
+    // 置顶消息，不支持置顶的平台默认什么都不做
+    async fn pin(&self, _message_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn unpin(&self, _message_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    // 修改已推送的消息
+    async fn edit(&self, _message_id: &str, _content: &str) -> Result<()> {
+        Err(unsupported(self.name(), "editing posts"))
+    }
+
+    // 删除已推送的消息
+    async fn delete(&self, _message_id: &str) -> Result<()> {
+        Err(unsupported(self.name(), "deleting posts"))
+    }
+
+    // 有讨论区的平台在主消息下回复的完整内容，返回 None 表示不回复
+    async fn discussion_content_by_repo(&self, _repo: &Repo) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    // 是否能通过 reply 回复已推送的消息，reply_on_retrend 依赖该能力
+    fn supports_reply(&self) -> bool {
+        false
+    }
+
+    // 在 message_id 对应消息的讨论区中回复
+    async fn reply(&self, _message_id: &str, _content: &str) -> Result<()> {
+        Err(unsupported(self.name(), "discussion replies"))
+    }
+
+    async fn content_by_developer(&self, developer: &Developer) -> Result<String> {
+        let template = self
+            .options()
+            .developer_template
+            .as_deref()
+            .unwrap_or(format::DEFAULT_DEVELOPER_TEMPLATE);
+        Ok(format::render_developer(template, self.options(), developer))
+    }
 }
\ No newline at end of file
@@ -1,7 +1,13 @@
+use std::fmt;
+use std::sync::{Arc, RwLock};
 use url::form_urlencoded;
 use async_trait::async_trait;
-use super::types::Platform;
+use super::format;
+use super::types::{Platform, PlatformOptions, RenderMode, RepoContent};
+use crate::http;
 use crate::repo::Repo;
+use log::warn;
+use reqwest::StatusCode;
 use anyhow::{anyhow, Context, Result};
 use reqwest_middleware::ClientBuilder;
 use reqwest_retry::policies::ExponentialBackoff;
@@ -9,18 +15,82 @@ use reqwest_retry::RetryTransientMiddleware;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
+// 知识星球的长度按 UTF-8 字节数计算（偏保守），中文每个字占 3 个字节
 const MAX_LENGTH: usize = 10000;
+const DEFAULT_TEMPLATE: &str = "{content}\n\n{url}\n\n{tags}";
+// 接口返回的未登录错误码
+const NOT_LOGGED_IN_CODE: i64 = 401;
 
 #[derive(Deserialize, Clone)]
 pub struct Zsxq {
     cookie: String,
     group_id: String,
     tags: Option<Vec<String>>,
+    // 标签之间的分隔符，默认为空格
+    tag_separator: Option<String>,
+    // 标签前的说明文字，例如 "标签："
+    tag_prefix: Option<String>,
+    // 是否按字母顺序排列标签，默认保持配置顺序
+    #[serde(default)]
+    sort_tags: bool,
+    // cookie 过期后用于重新认证的接口和 refresh token
+    reauth_url: Option<String>,
+    refresh_token: Option<String>,
+    #[serde(skip)]
+    refreshed_cookie: Arc<RwLock<Option<String>>>,
+    #[serde(flatten)]
+    options: PlatformOptions,
+    template: Option<String>,
 }
 
-#[async_trait]
-impl Platform for Zsxq {
-    async fn post(&self, content: &str) -> Result<()> {
+// 知识星球返回未登录，cookie 已过期
+#[derive(Debug)]
+struct CookieExpired;
+
+impl fmt::Display for CookieExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "zsxq cookie expired")
+    }
+}
+
+impl std::error::Error for CookieExpired {}
+
+impl Zsxq {
+    // 重新认证后的 cookie 优先于配置文件中的 cookie
+    fn cookie(&self) -> String {
+        self.refreshed_cookie
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| self.cookie.clone())
+    }
+
+    // 向 reauth_url 提交 refresh_token，响应为 {"cookie": "..."} 或 cookie 文本
+    async fn reauth(&self) -> Result<()> {
+        let Some(url) = &self.reauth_url else {
+            return Err(anyhow!("zsxq.reauth_url is not configured"));
+        };
+        let text = http::client()
+            .post(url)
+            .timeout(core::time::Duration::from_secs(60))
+            .json(&json!({"refresh_token": self.refresh_token}))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let cookie = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|resp| resp["cookie"].as_str().map(String::from))
+            .unwrap_or_else(|| text.trim().to_string());
+        if cookie.is_empty() {
+            return Err(anyhow!("zsxq re-auth returned an empty cookie"));
+        }
+        *self.refreshed_cookie.write().unwrap() = Some(cookie);
+        Ok(())
+    }
+
+    async fn create_topic(&self, content: &str) -> Result<Option<String>> {
         let url = format!("https://api.zsxq.com/v2/groups/{}/topics", self.group_id);
         let data = json!({
             "req_data": {
@@ -33,14 +103,14 @@ impl Platform for Zsxq {
         });
 
         let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-        let client = ClientBuilder::new(reqwest::Client::new())
+        let client = ClientBuilder::new(http::client())
             .with(RetryTransientMiddleware::new_with_policy(retry_policy))
             .build();
 
         let resp_str = client.post(url)
             .timeout(core::time::Duration::from_secs(60))
             .json(&data)
-            .header("cookie", &self.cookie)
+            .header("cookie", self.cookie())
             .send()
             .await?
             .error_for_status()?
@@ -48,11 +118,17 @@ impl Platform for Zsxq {
             .await?;
 
         let resp: Value = serde_json::from_str(resp_str.as_str())?;
+        if resp["code"].as_i64() == Some(NOT_LOGGED_IN_CODE) {
+            return Err(anyhow!(CookieExpired));
+        }
         match resp["succeeded"].as_bool() {
             None => Err(anyhow!("post zsxq failed: {}", resp_str)),
             Some(b) => {
                 if b {
-                    Ok(())
+                    let topic_id = resp["resp_data"]["topic"]["topic_id"]
+                        .as_i64()
+                        .map(|id| id.to_string());
+                    Ok(topic_id)
                 } else {
                     Err(anyhow!("post zsxq failed: {}, error: {}", resp_str, resp["error"].to_string()))
                 }
@@ -60,14 +136,143 @@ impl Platform for Zsxq {
         }
     }
 
-    async fn content_by_repo(&self, repo: &Repo) -> Result<String> {
-        let url = repo.get_url();
-        let tags = self.tags.clone().unwrap_or(vec![]).iter().map(|val| {
+    // 在主题下发表评论，主题已被删除时接口返回失败
+    async fn create_comment(&self, topic_id: &str, content: &str) -> Result<()> {
+        let url = format!("https://api.zsxq.com/v2/topics/{}/comments", topic_id);
+        let data = json!({
+            "req_data": {
+                "text": content,
+                "image_ids": [],
+                "mentioned_user_ids": []
+            }
+        });
+        let resp: Value = http::client()
+            .post(url)
+            .timeout(core::time::Duration::from_secs(60))
+            .json(&data)
+            .header("cookie", self.cookie())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if resp["code"].as_i64() == Some(NOT_LOGGED_IN_CODE) {
+            return Err(anyhow!(CookieExpired));
+        }
+        if resp["succeeded"].as_bool() != Some(true) {
+            return Err(anyhow!("comment on zsxq topic {} failed: {}", topic_id, resp));
+        }
+        Ok(())
+    }
+
+    // cookie 过期时重新认证，之后由调用方重试；其他错误原样返回
+    async fn reauth_if_expired(&self, e: anyhow::Error) -> Result<()> {
+        if !e.is::<CookieExpired>() && http::error_status(&e) != Some(StatusCode::UNAUTHORIZED) {
+            return Err(e);
+        }
+        if self.reauth_url.is_none() {
+            return Err(e.context("zsxq cookie expired, please update zsxq.cookie"));
+        }
+        warn!("zsxq cookie expired, re-authenticating");
+        self.reauth().await.context("While re-authenticating zsxq")
+    }
+
+    // 设置或取消主题置顶
+    async fn sticky(&self, method: reqwest::Method, topic_id: &str) -> Result<()> {
+        let url = format!("https://api.zsxq.com/v2/topics/{}/sticky", topic_id);
+        let resp: Value = http::client()
+            .request(method, url)
+            .timeout(core::time::Duration::from_secs(60))
+            .header("cookie", self.cookie())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if resp["succeeded"].as_bool() != Some(true) {
+            return Err(anyhow!("zsxq sticky failed: {}", resp));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Platform for Zsxq {
+    fn name(&self) -> &str {
+        "zsxq"
+    }
+
+    fn options(&self) -> &PlatformOptions {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut PlatformOptions {
+        &mut self.options
+    }
+
+    async fn post(&self, content: &str) -> Result<Option<String>> {
+        match self.create_topic(content).await {
+            Err(e) => {
+                self.reauth_if_expired(e).await?;
+                self.create_topic(content).await
+            }
+            result => result,
+        }
+    }
+
+    fn supports_reply(&self) -> bool {
+        true
+    }
+
+    // 知识星球没有讨论区，回复即在主题下评论
+    async fn reply(&self, message_id: &str, content: &str) -> Result<()> {
+        match self.create_comment(message_id, content).await {
+            Err(e) => {
+                self.reauth_if_expired(e).await?;
+                self.create_comment(message_id, content).await
+            }
+            result => result,
+        }
+    }
+
+    async fn pin(&self, message_id: &str) -> Result<()> {
+        self.sticky(reqwest::Method::POST, message_id).await.context("While pinning zsxq topic")
+    }
+
+    async fn unpin(&self, message_id: &str) -> Result<()> {
+        self.sticky(reqwest::Method::DELETE, message_id).await.context("While unpinning zsxq topic")
+    }
+
+    async fn content_by_repo(&self, repo: &Repo) -> Result<RepoContent> {
+        let mut names = self.tags.clone().unwrap_or(vec![]);
+        if self.sort_tags {
+            names.sort();
+        }
+        if self.options.source_language_tag {
+            if let Some(language) = format::source_language_name(repo) {
+                names.insert(0, language);
+            }
+        }
+        let mut tags = names.iter().map(|val| {
             tag(val)
-        }).collect::<Vec<String>>().join(" ");
-        let length_left = MAX_LENGTH - (url.len() + tags.len());
-        let content = repo.get_content(length_left).await.context("While getting repo content")?;
-        Ok(format!("{}\n\n{}\n\n{}", content, url, tags))
+        }).collect::<Vec<String>>().join(self.tag_separator.as_deref().unwrap_or(" "));
+        if let (Some(prefix), false) = (&self.tag_prefix, tags.is_empty()) {
+            tags = format!("{}{}", prefix, tags);
+        }
+        let mut template = format::repo_template(&self.options, repo, self.template.as_deref(), DEFAULT_TEMPLATE);
+        // 卡片格式不包含 {tags}，标签追加在卡片下方
+        if self.options.render_mode == RenderMode::Card && !tags.is_empty() {
+            template.push_str("\n{tags}");
+        }
+        let render = |content: &str| format::render_repo(&template, &self.options, repo, &[("tags", &tags)], content);
+        let max_length = self.options.max_length(MAX_LENGTH);
+        let length_left = max_length.saturating_sub(render("").len());
+        let content = repo.get_content(&self.options, length_left, str::len).await.context("While getting repo content")?;
+        Ok(RepoContent {
+            content: format::fit_content(render, &content.content, max_length, str::len),
+            summary: content.content,
+            token_usage: content.total_tokens,
+        })
     }
 }
 
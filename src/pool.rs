@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use redis::aio::Connection;
+use redis::{Client, Cmd, FromRedisValue};
+
+struct Replica {
+    client: Client,
+    conn: Option<Connection>,
+}
+
+// 第一个地址为主库，用于写入；其余为从库，读取时依次尝试，最后回退到主库
+pub struct RedisPool {
+    primary: Connection,
+    replicas: Vec<Replica>,
+}
+
+impl RedisPool {
+    pub async fn connect(urls: &[String]) -> Result<Self> {
+        let (primary_url, replica_urls) = urls
+            .split_first()
+            .ok_or_else(|| anyhow!("redis.url must not be empty"))?;
+        let primary = Client::open(primary_url.as_str())
+            .context("While creating redis client")?
+            .get_async_connection()
+            .await
+            .context("While connecting redis")?;
+        let replicas = replica_urls
+            .iter()
+            .map(|url| {
+                Ok(Replica {
+                    client: Client::open(url.as_str()).context("While creating redis replica client")?,
+                    conn: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RedisPool { primary, replicas })
+    }
+
+    pub fn writer(&mut self) -> &mut Connection {
+        &mut self.primary
+    }
+
+    pub async fn exists(&mut self, key: &str) -> Result<bool> {
+        self.read(redis::cmd("EXISTS").arg(key)).await
+    }
+
+    pub async fn get(&mut self, key: &str) -> Result<Option<String>> {
+        self.read(redis::cmd("GET").arg(key)).await
+    }
+
+    pub async fn is_member(&mut self, key: &str, member: &str) -> Result<bool> {
+        self.read(redis::cmd("SISMEMBER").arg(key).arg(member)).await
+    }
+
+    async fn read<T: FromRedisValue>(&mut self, cmd: &Cmd) -> Result<T> {
+        for replica in self.replicas.iter_mut() {
+            match replica.read(cmd).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    // 丢弃失效的连接，下次读取时重连
+                    replica.conn = None;
+                    warn!("redis replica read failed: {:#}", e);
+                }
+            }
+        }
+        Ok(cmd.query_async(&mut self.primary).await?)
+    }
+}
+
+impl Replica {
+    async fn read<T: FromRedisValue>(&mut self, cmd: &Cmd) -> Result<T> {
+        if self.conn.is_none() {
+            self.conn = Some(self.client.get_async_connection().await?);
+        }
+        let conn = self.conn.as_mut().unwrap();
+        Ok(cmd.query_async(conn).await?)
+    }
+}
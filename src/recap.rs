@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveTime};
+use chrono_tz::Tz;
+use redis::AsyncCommands;
+use crate::config::RecapConfig;
+
+// 每天推送的项目保存 8 天，足够覆盖一周的汇总
+const DAY_TTL: usize = 8 * 24 * 3600;
+
+fn day_key(config: &RecapConfig, date: &str) -> String {
+    format!("{}:posted:{}", config.key, date)
+}
+
+// 推送成功时记录项目当时的 star 数，多个平台推送同一项目时保留第一次的值
+pub async fn record(
+    conn: &mut redis::aio::Connection,
+    config: &RecapConfig,
+    id: &str,
+    stars: u64,
+    now: DateTime<Tz>,
+) -> Result<()> {
+    let key = day_key(config, &now.date_naive().to_string());
+    conn.hset_nx::<_, _, _, ()>(&key, id, stars).await?;
+    conn.expire::<_, ()>(&key, DAY_TTL).await?;
+    Ok(())
+}
+
+fn week_id(now: DateTime<Tz>) -> String {
+    let week = now.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+fn last_week_key(config: &RecapConfig) -> String {
+    format!("{}:last_week", config.key)
+}
+
+// 到达配置的时间且本周还没有发送过
+pub async fn is_due(conn: &mut redis::aio::Connection, config: &RecapConfig, now: DateTime<Tz>) -> Result<bool> {
+    let time = NaiveTime::parse_from_str(&config.time, "%H:%M")?;
+    if now.weekday() != config.weekday || now.time() < time {
+        return Ok(false);
+    }
+    let last: Option<String> = conn.get(last_week_key(config)).await?;
+    Ok(last != Some(week_id(now)))
+}
+
+pub async fn mark_sent(conn: &mut redis::aio::Connection, config: &RecapConfig, now: DateTime<Tz>) -> Result<()> {
+    conn.set::<_, _, ()>(last_week_key(config), week_id(now)).await?;
+    Ok(())
+}
+
+// 最近 7 天推送过的项目及推送时的 star 数，同一项目推送过多次时取最早的值
+pub async fn posted(
+    conn: &mut redis::aio::Connection,
+    config: &RecapConfig,
+    now: DateTime<Tz>,
+) -> Result<HashMap<String, u64>> {
+    let mut posted: HashMap<String, u64> = HashMap::new();
+    for days in 0..7 {
+        let date = now.date_naive() - Duration::days(days);
+        let stars: HashMap<String, u64> = conn.hgetall(day_key(config, &date.to_string())).await?;
+        for (id, count) in stars {
+            posted.entry(id).and_modify(|stars| *stars = (*stars).min(count)).or_insert(count);
+        }
+    }
+    Ok(posted)
+}
+
+// 按推送后的 star 增长排序，返回增长最多的 top 个项目，没有当前 star 数的项目不参与排序
+pub fn top(config: &RecapConfig, posted: &HashMap<String, u64>, current: &HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut deltas: Vec<(String, u64)> = posted
+        .iter()
+        .filter_map(|(id, stars)| Some((id.clone(), current.get(id)?.saturating_sub(*stars))))
+        .collect();
+    deltas.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    deltas.truncate(config.top);
+    deltas
+}
+
+pub fn compose(config: &RecapConfig, top: &[(String, u64)]) -> String {
+    let lines: Vec<String> = top
+        .iter()
+        .enumerate()
+        .map(|(i, (id, stars))| format!("{}. {} +{} stars\nhttps://github.com/{}", i + 1, id, stars, id))
+        .collect();
+    format!("{}\n\n{}", config.title, lines.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::top;
+    use crate::config::RecapConfig;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_top_by_delta_since_posting() {
+        let config: RecapConfig = toml::from_str("top = 2").unwrap();
+        let posted = HashMap::from([
+            ("a/a".to_string(), 100),
+            ("b/b".to_string(), 5000),
+            ("c/c".to_string(), 10),
+            ("d/d".to_string(), 10),
+        ]);
+        let current = HashMap::from([
+            ("a/a".to_string(), 400),
+            ("b/b".to_string(), 5100),
+            ("c/c".to_string(), 1000),
+        ]);
+        assert_eq!(
+            top(&config, &posted, &current),
+            vec![("c/c".to_string(), 990), ("a/a".to_string(), 300)]
+        );
+    }
+}
@@ -1,20 +1,109 @@
+use std::collections::HashSet;
+use std::future::Future;
 use std::convert::TryInto;
-use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
-use reqwest;
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use unicode_segmentation::UnicodeSegmentation;
-use anyhow::{Context, Result};
-use log::info;
+use anyhow::{anyhow, Context, Result};
+use futures::{stream, StreamExt};
+use log::{debug, error, info, warn};
 use redis::AsyncCommands;
-use crate::openai::{chat_completion, read_url};
+use crate::config::{ExternalDedup, FilterConfig, SamplingConfig, SearchConfig, TrendingConfig, TrendingMode};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::github::{self, Release, RepoMetadata, UserMetadata};
+use crate::http;
+use crate::openai::{self, chat_completion, clean_output, read_url, Completion};
+use crate::platform::format;
+use crate::platform::types::{PlatformOptions, PrimaryContent, ShortContentAction, ThinReadmeAction};
+use crate::pool::RedisPool;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
-#[derive(Deserialize, Debug)]
+const STARS_TTL: usize = 30 * 24 * 3600;
+// 解析出的异常项目超过该比例时重新获取页面
+const DEFAULT_MAX_MALFORMED_RATIO: f64 = 0.2;
+// 演示图片的最大体积
+const MAX_MEDIA_SIZE: usize = 5 * 1024 * 1024;
+// 同时获取的语言数量
+const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+// why_trending 提供给模型的最近提交数量
+const WHY_TRENDING_COMMITS: usize = 5;
+pub const DEFAULT_WHY_TRENDING_PROMPT: &str =
+    "根据以下开源项目的 star 增长和最近动态，用一句中文说明它最近为什么受到关注，不超过 50 字，只返回这句话：";
+
+// README 中的演示图片或 GIF
+pub struct DemoMedia {
+    pub url: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Deserialize, Debug, Default)]
 #[cfg_attr(test, derive(Clone, PartialEq, Eq))]
 pub struct Repo {
     pub author: String,
     pub description: String,
     pub name: String,
+    // 项目的编程语言，页面上没有时为空
+    #[serde(default)]
+    pub language: String,
+    // 获取该项目时使用的 trending 语言
+    #[serde(default)]
+    pub source_language: String,
+    // 获取该项目时使用的时间范围（daily / weekly / monthly）和自然语言
+    #[serde(default)]
+    pub since: String,
+    #[serde(default)]
+    pub spoken_language: String,
+    // 在 trending 列表中的排名，从 1 开始
+    #[serde(default)]
+    pub rank: usize,
+    #[serde(default)]
+    pub stars: u64,
+    #[serde(default)]
+    pub stars_today: u64,
+    // 与上次获取时相比的 star 增量，没有历史记录时等于 stars_today
+    #[serde(default)]
+    pub stars_delta: i64,
+    #[serde(default)]
+    pub translated_description: Option<String>,
+    // 模型翻译或音译的项目名称，未开启 translate_name 时为空
+    #[serde(default)]
+    pub translated_name: String,
+    // 通过 GitHub API 获取的 open issue 和 PR 数量，未获取时为 None
+    #[serde(default)]
+    pub open_issues: Option<u64>,
+    #[serde(default)]
+    pub open_prs: Option<u64>,
+    // GitHub 项目的 topics，用于 {topics}，获取失败时为空
+    #[serde(default)]
+    pub topics: Vec<String>,
+    // 作者是个人还是组织，以及关注者数量，用于 {author_type} {author_followers}
+    #[serde(default)]
+    pub author_type: String,
+    #[serde(default)]
+    pub author_followers: Option<u64>,
+    // 最近一次推送代码的时间戳，用于 {updated}
+    #[serde(default)]
+    pub pushed_at: Option<u64>,
+    // 最新 release 的 tag 和发布日期（YYYY-MM-DD），没有 release 时为空
+    #[serde(default)]
+    pub latest_release: String,
+    #[serde(default)]
+    pub release_date: String,
+    // 模型根据 star 增长和最近动态生成的上榜原因，用于 {why_trending}
+    #[serde(default)]
+    pub why_trending: String,
+    // 来自 fast_path.authors 的项目直接使用描述，不调用模型生成简介
+    #[serde(default)]
+    pub fast_path: bool,
+    // 来自 trending.dedup_per_context，按上榜条件分别去重
+    #[serde(default)]
+    pub dedup_per_context: bool,
 }
 
 impl Repo {
@@ -22,27 +111,506 @@ impl Repo {
         format!("https://github.com/{}/{}", self.author, self.name)
     }
 
-    pub async fn get_content(&self, max_length: usize) -> Result<String> {
+    // 用于去重、审计等场景的唯一标识
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.author, self.name)
+    }
+
+    // 上榜条件，例如 rust/daily/zh，用于 {trending_context}
+    pub fn trending_context(&self) -> String {
+        let language = if self.source_language.is_empty() { "all" } else { &self.source_language };
+        let mut parts = vec![language, &self.since];
+        if !self.spoken_language.is_empty() {
+            parts.push(&self.spoken_language);
+        }
+        parts.join("/")
+    }
+
+    // 推送去重使用的 id，开启 dedup_per_context 时同一项目在不同上榜条件下分别推送
+    pub fn post_id(&self) -> String {
+        if self.dedup_per_context {
+            format!("{}@{}", self.id(), self.trending_context())
+        } else {
+            self.id()
+        }
+    }
+
+    // 从 post_id 中取出 author/name，项目名中不会出现 @
+    pub fn id_of_post(post_id: &str) -> &str {
+        post_id.split_once('@').map_or(post_id, |(id, _)| id)
+    }
+
+    // 按平台的 summary_prompt / summary_length 生成简介，未设置时使用全局提示词
+    // max_length 按平台的 measure 计算，summary_length 始终按字素计算
+    // 返回的 total_tokens 为生成简介时调用模型消耗的 token 数
+    pub async fn get_content(
+        &self,
+        options: &PlatformOptions,
+        max_length: usize,
+        measure: fn(&str) -> usize,
+    ) -> Result<Completion> {
+        let fit = |completion: Completion| {
+            let content = match options.summary_length {
+                Some(length) => truncate(&truncate(&completion.content, length, format::grapheme_count), max_length, measure),
+                None => truncate(&completion.content, max_length, measure),
+            };
+            Completion { content, ..completion }
+        };
+        let text = |content: &str| Completion {
+            content: content.to_string(),
+            total_tokens: 0,
+        };
+        // 描述为空时双语、fast_path 和 description 模式都没有可用的内容，改为总结 README
+        let has_description = !self.description.trim().is_empty();
+        if options.bilingual && has_description {
+            let completion = match options.summary_length {
+                Some(length) => self.get_bilingual_content(length, format::grapheme_count).await?,
+                None => self.get_bilingual_content(max_length, measure).await?,
+            };
+            return Ok(Completion {
+                content: truncate(&completion.content, max_length, measure),
+                ..completion
+            });
+        }
+        if self.fast_path && has_description {
+            let description = self.translated_description.as_deref().unwrap_or(&self.description);
+            return Ok(fit(text(description)));
+        }
+        if options.primary_content == PrimaryContent::Description && has_description {
+            return Ok(fit(self.description_content().await?));
+        }
+        if !openai::is_configured() {
+            return Ok(fit(text(&self.description)));
+        }
         let url = self.get_url();
         let repo_content = read_url(&url).await.context("While read url")?;
-        let prompt = format!("假设你是一名资深技术专家，精通各种开源项目，请基于以下开源项目内容写一段简介内容，用中文回答：{}", repo_content);
-        let content = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?;
-        Ok(truncate(&content, max_length))
+        // README 过短时模型容易编造内容，不再总结
+        if let Some(min_length) = options.min_readme_length {
+            if is_thin_readme(&repo_content, min_length) {
+                if options.thin_readme == ThinReadmeAction::Description && has_description {
+                    info!("readme of {} is too short, using description", self.id());
+                    return Ok(fit(self.description_content().await?));
+                }
+                return Err(anyhow!("readme of {} is shorter than {}", self.id(), min_length));
+            }
+        }
+        let mut prompt = options.summary_prompt.clone().unwrap_or_else(openai::summary_prompt);
+        if let Some(length) = options.summary_length {
+            prompt = format!("{}（不超过 {} 字）", prompt, length);
+        }
+        // 结构化简介失败后改为普通简介，两次请求的 token 都计入
+        let mut total_tokens = 0;
+        let structured = if options.structured_summary {
+            let prompt = format!("{}{}{}", prompt, STRUCTURED_SUMMARY_PROMPT, repo_content);
+            match self.get_structured_content(&prompt, options, &mut total_tokens).await {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    warn!("structured summary of {} failed, falling back to plain text: {:#}", self.id(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let prompt = format!("{}{}", prompt, repo_content);
+        let mut content = match structured {
+            Some(content) => content,
+            None => {
+                let completion = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?;
+                total_tokens += completion.total_tokens;
+                clean_output(&completion.content)
+            }
+        };
+        if let Some(min_length) = options.min_content_length {
+            if content.graphemes(true).count() < min_length {
+                match options.short_content {
+                    ShortContentAction::Skip => {
+                        return Err(anyhow!("summary of {} is shorter than {}", self.id(), min_length));
+                    }
+                    // 只重新生成一次，仍然过短时照常推送
+                    ShortContentAction::Reprompt => {
+                        info!("summary of {} is too short, asking for more detail", self.id());
+                        let prompt = format!("{}\n\n请写得更详细一些，不少于 {} 字。", prompt, min_length);
+                        let completion = chat_completion(&prompt).await.context("While re-prompting summary")?;
+                        total_tokens += completion.total_tokens;
+                        content = clean_output(&completion.content);
+                    }
+                }
+            }
+        }
+        Ok(fit(Completion { content, total_tokens }))
+    }
+
+    // 翻译后的描述，未配置 OpenAI 时使用原文
+    async fn description_content(&self) -> Result<Completion> {
+        let text = |content: &str| Completion {
+            content: content.to_string(),
+            total_tokens: 0,
+        };
+        match &self.translated_description {
+            Some(translated) => Ok(text(translated)),
+            None if openai::is_configured() => self.get_chinese_description().await.context("While translating description"),
+            None => Ok(text(&self.description)),
+        }
+    }
+
+    // 请求成功但输出无法解析时，消耗的 token 同样计入 total_tokens
+    async fn get_structured_content(&self, prompt: &str, options: &PlatformOptions, total_tokens: &mut u64) -> Result<String> {
+        let output = openai::chat_completion_json(prompt).await?;
+        *total_tokens += output.total_tokens;
+        let summary: StructuredSummary = serde_json::from_str(&clean_output(&output.content))
+            .context(format!("invalid structured summary: {}", output.content))?;
+        let template = options.summary_template.as_deref().unwrap_or(DEFAULT_SUMMARY_TEMPLATE);
+        Ok(summary.render(template))
+    }
+
+    // 下载 README 中第一张非徽章图片，不是图片或体积过大时返回 None
+    pub async fn demo_media(&self) -> Result<Option<DemoMedia>> {
+        let readme = read_url(&self.get_url()).await.context("While read url")?;
+        let Some(url) = first_image_url(&readme) else {
+            return Ok(None);
+        };
+        let resp = http::client()
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await?
+            .error_for_status()?;
+        let mime_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if !mime_type.starts_with("image/") {
+            info!("skip demo media {} with content type {}", url, mime_type);
+            return Ok(None);
+        }
+        let data = resp.bytes().await?.to_vec();
+        if data.len() > MAX_MEDIA_SIZE {
+            info!("skip demo media {} with size {}", url, data.len());
+            return Ok(None);
+        }
+        Ok(Some(DemoMedia { url, mime_type, data }))
+    }
+
+    // 原文描述和中文翻译各占一行，原文最多占用一半长度
+    async fn get_bilingual_content(&self, max_length: usize, measure: fn(&str) -> usize) -> Result<Completion> {
+        let translated = match &self.translated_description {
+            Some(translated) => Completion {
+                content: translated.clone(),
+                total_tokens: 0,
+            },
+            None if openai::is_configured() && !self.description.is_empty() => {
+                self.get_chinese_description().await.context("While translating description")?
+            }
+            None => {
+                return Ok(Completion {
+                    content: self.description.clone(),
+                    total_tokens: 0,
+                })
+            }
+        };
+        if translated.content == self.description {
+            return Ok(Completion {
+                content: self.description.clone(),
+                ..translated
+            });
+        }
+        let original = truncate(&self.description, max_length / 2, measure);
+        let length_left = max_length.saturating_sub(measure(&original) + 1);
+        Ok(Completion {
+            content: format!("{}\n{}", original, truncate(&translated.content, length_left, measure)),
+            ..translated
+        })
+    }
+
+    // 跟随仓库地址的重定向，更新为改名后的 author/name
+    pub async fn canonicalize(&mut self) -> Result<()> {
+        let resp = http::client()
+            .head(self.get_url())
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut segments = resp
+            .url()
+            .path_segments()
+            .ok_or_else(|| anyhow!("invalid repo url: {}", resp.url()))?;
+        if let (Some(author), Some(name)) = (segments.next(), segments.next()) {
+            if author != self.author || name != self.name {
+                info!("repo {}/{} redirected to {}/{}", self.author, self.name, author, name);
+                self.author = author.to_string();
+                self.name = name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    // open_issues_count 同时包含 issue 和 PR，需要通过搜索接口获取 PR 数量后相减
+    pub async fn fetch_issue_counts(&mut self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        let total = self.metadata(conn, ttl).await?.open_issues_count;
+        let prs = cached(conn, &format!("open_prs:{}", self.id()), ttl, || async {
+            let search = github::get(&format!(
+                "/search/issues?q=repo:{}/{}+type:pr+state:open&per_page=1",
+                self.author, self.name
+            ))
+            .await?;
+            search["total_count"]
+                .as_u64()
+                .ok_or_else(|| anyhow!("total_count not found"))
+        })
+        .await?;
+        self.open_prs = Some(prs);
+        self.open_issues = Some(total.saturating_sub(prs));
+        Ok(())
+    }
+
+    // 最多保留 max 个 topic
+    pub async fn fetch_topics(&mut self, conn: &mut redis::aio::Connection, ttl: usize, max: usize) -> Result<()> {
+        let mut topics = self.metadata(conn, ttl).await?.topics;
+        topics.truncate(max);
+        self.topics = topics;
+        Ok(())
+    }
+
+    // 解析不到时间时保持 None，{updated} 渲染为空
+    pub async fn fetch_pushed_at(&mut self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        let pushed_at = self.metadata(conn, ttl).await?.pushed_at;
+        self.pushed_at = pushed_at
+            .as_deref()
+            .and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok())
+            .map(|t| t.unix_timestamp() as u64);
+        Ok(())
+    }
+
+    // 最新 release 缓存在 Redis 中，没有 release 时缓存 null，避免重复请求
+    pub async fn release(&self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<Option<Release>> {
+        let key = format!("release:{}", self.id());
+        let cached: Option<String> = conn.get(&key).await?;
+        if let Some(release) = cached.and_then(|value| serde_json::from_str(&value).ok()) {
+            return Ok(release);
+        }
+        let release = github::latest_release(&self.author, &self.name).await?;
+        conn.set_ex::<_, _, ()>(&key, serde_json::to_string(&release)?, ttl).await?;
+        Ok(release)
+    }
+
+    pub async fn fetch_release(&mut self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        if let Some(release) = self.release(conn, ttl).await? {
+            self.set_release(&release);
+        }
+        Ok(())
+    }
+
+    fn set_release(&mut self, release: &Release) {
+        self.latest_release = release.tag_name.clone();
+        self.release_date = release
+            .published_at
+            .as_deref()
+            .and_then(|s| s.get(..10))
+            .unwrap_or_default()
+            .to_string();
+    }
+
+    // 作者信息按作者缓存，同一作者的多个项目共用
+    pub async fn fetch_author_info(&mut self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        let key = format!("author:{}", self.author);
+        let cached: Option<String> = conn.get(&key).await?;
+        let user = match cached.and_then(|value| serde_json::from_str::<UserMetadata>(&value).ok()) {
+            Some(user) => user,
+            None => {
+                let user = github::user_metadata(&self.author).await?;
+                conn.set_ex::<_, _, ()>(&key, serde_json::to_string(&user)?, ttl).await?;
+                user
+            }
+        };
+        self.author_type = user.user_type;
+        self.author_followers = Some(user.followers);
+        Ok(())
+    }
+
+    // 将 star 增量、最近的提交和 release 提供给模型，避免模型凭空猜测上榜原因
+    // 已经通过 trending.releases 或 require_recent_release 获取过 release 时直接复用，否则读取缓存
+    pub async fn fetch_why_trending(&mut self, prompt: &str, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        let commits = cached(conn, &format!("commits:{}", self.id()), ttl, || {
+            github::recent_commits(&self.author, &self.name, WHY_TRENDING_COMMITS)
+        })
+        .await
+        .context("While fetching recent commits")?;
+        if self.latest_release.is_empty() {
+            if let Some(release) = self.release(conn, ttl).await.context("While fetching latest release")? {
+                self.set_release(&release);
+            }
+        }
+        let release = (!self.latest_release.is_empty()).then(|| format!("{} {}", self.release_date, self.latest_release));
+        let prompt = format!("{}\n{}", prompt, self.why_trending_context(&commits, release.as_deref()));
+        let content = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?.content;
+        self.why_trending = clean_output(&content);
+        Ok(())
+    }
+
+    fn why_trending_context(&self, commits: &[String], release: Option<&str>) -> String {
+        let mut lines = vec![
+            format!("项目：{}", self.id()),
+            format!("描述：{}", self.description),
+            format!("总 star 数：{}", self.stars),
+            format!("今日新增 star：{}", self.stars_today),
+        ];
+        if !commits.is_empty() {
+            lines.push(format!("最近的提交：\n{}", commits.join("\n")));
+        }
+        if let Some(release) = release {
+            lines.push(format!("最新 release：{}", release));
+        }
+        lines.join("\n")
+    }
+
+    // 通过 GitHub API 获取项目信息，缓存在 Redis 中，同一轮的多个功能共用一次请求
+    pub async fn metadata(&self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<RepoMetadata> {
+        let key = format!("metadata:{}", self.id());
+        let cached: Option<String> = conn.get(&key).await?;
+        if let Some(metadata) = cached.and_then(|value| serde_json::from_str(&value).ok()) {
+            return Ok(metadata);
+        }
+        let metadata = github::repo_metadata(&self.author, &self.name).await?;
+        conn.set_ex::<_, _, ()>(&key, serde_json::to_string(&metadata)?, ttl).await?;
+        Ok(metadata)
+    }
+
+    // 翻译结果缓存在 Redis 中，同一项目再次上榜时不重复调用模型
+    pub async fn fetch_translated_name(&mut self, conn: &mut redis::aio::Connection, ttl: usize) -> Result<()> {
+        let key = format!("translated_name:{}", self.id());
+        if let Some(cached) = conn.get::<_, Option<String>>(&key).await? {
+            self.translated_name = cached;
+            return Ok(());
+        }
+        let prompt = format!(
+            "请将以下开源项目的名称翻译或音译成简短的中文名称，只返回名称：{}\n项目描述：{}",
+            self.name, self.description
+        );
+        let content = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?.content;
+        self.translated_name = clean_output(&content);
+        conn.set_ex::<_, _, ()>(&key, &self.translated_name, ttl).await?;
+        Ok(())
+    }
+
+    pub async fn get_chinese_description(&self) -> Result<Completion> {
+        let prompt = format!("请将以下开源项目的描述翻译成中文，只返回翻译结果：{}", self.description);
+        let completion = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?;
+        Ok(Completion {
+            content: clean_output(&completion.content),
+            ..completion
+        })
     }
 }
 
-fn truncate(content: &str, max_length: usize) -> String {
-    if content.graphemes(true).count() < max_length {
-        content.to_string()
-    } else {
-        format!(
-            "{} ...",
-            content
-                .graphemes(true)
-                .take(max_length - 4)
-                .collect::<String>()
+// 将所有待翻译的描述合并到一次请求中，按编号解析结果，解析失败时逐个翻译
+pub async fn translate_descriptions(repos: &mut [Repo]) -> Result<()> {
+    let mut pending: Vec<&mut Repo> = repos
+        .iter_mut()
+        .filter(|repo| repo.translated_description.is_none() && !repo.description.is_empty())
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let numbered = pending
+        .iter()
+        .enumerate()
+        .map(|(i, repo)| format!("{}. {}", i + 1, repo.description.replace('\n', " ")))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let prompt = format!("请将以下编号的开源项目描述逐条翻译成中文，保持相同的编号格式，每行一条，只返回翻译结果：\n{}", numbered);
+    let content = chat_completion(&prompt).await.context(format!("prompt: {}", prompt))?.content;
+
+    match parse_numbered(&content, pending.len()) {
+        Some(translations) => {
+            for (repo, translation) in pending.iter_mut().zip(translations) {
+                repo.translated_description = Some(translation);
+            }
+        }
+        None => {
+            warn!("batch translation mismatch, falling back to per-repo translation");
+            // 单个翻译失败时保留原文描述，不影响其他项目
+            for repo in pending.iter_mut() {
+                match repo.get_chinese_description().await {
+                    Ok(translation) => repo.translated_description = Some(translation.content),
+                    Err(e) => warn!("translate description of {} failed: {:#}", repo.id(), e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_numbered(content: &str, count: usize) -> Option<Vec<String>> {
+    let mut result = vec![None; count];
+    for line in content.lines() {
+        let line = line.trim();
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            continue;
+        }
+        let index: usize = line[..digits].parse().ok()?;
+        let text = line[digits..].trim_start_matches(['.', '、', ')', ':']).trim();
+        if index == 0 || index > count || text.is_empty() {
+            return None;
+        }
+        result[index - 1] = Some(text.to_string());
+    }
+    result.into_iter().collect()
+}
+
+const STRUCTURED_SUMMARY_PROMPT: &str = "\n\n请只返回一个 JSON 对象，包含以下字段：title（项目名称和定位，10 字以内）、one_liner（一句话介绍）、features（主要特性，字符串数组，3 ~ 5 项）。\n\n";
+const DEFAULT_SUMMARY_TEMPLATE: &str = "{one_liner}\n\n{features}";
+
+// 模型按 JSON 返回的结构化简介
+#[derive(Deserialize)]
+struct StructuredSummary {
+    #[serde(default)]
+    title: String,
+    one_liner: String,
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+impl StructuredSummary {
+    // features 每项一行，以 - 开头
+    fn render(&self, template: &str) -> String {
+        let features = self
+            .features
+            .iter()
+            .map(|feature| format!("- {}", feature.trim()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format::render(
+            template,
+            &[
+                ("title", self.title.trim()),
+                ("one_liner", self.one_liner.trim()),
+                ("features", &features),
+            ],
         )
+        .trim()
+        .to_string()
+    }
+}
+
+// 按字素截短，使 measure 计算的长度（含省略号）不超过 max_length
+fn truncate(content: &str, max_length: usize, measure: fn(&str) -> usize) -> String {
+    if measure(content) <= max_length {
+        return content.to_string();
     }
+    let mut length = measure(" ...");
+    let mut truncated = String::new();
+    for grapheme in content.graphemes(true) {
+        length += measure(grapheme);
+        if length > max_length {
+            break;
+        }
+        truncated.push_str(grapheme);
+    }
+    format!("{} ...", truncated)
 }
 
 fn parse_trending(html: String) -> Result<Vec<Repo>> {
@@ -51,7 +619,8 @@ fn parse_trending(html: String) -> Result<Vec<Repo>> {
     let html = scraper::Html::parse_document(&html);
     let repos = html
         .select(&".Box article.Box-row".try_into().unwrap())
-        .filter_map(|repo| {
+        .enumerate()
+        .filter_map(|(i, repo)| {
             let title = repo
                 .select(&".h3".try_into().unwrap())
                 .next()?
@@ -59,24 +628,43 @@ fn parse_trending(html: String) -> Result<Vec<Repo>> {
                 .fold(String::new(), |acc, s| acc + s);
             let mut title_split = title.split('/');
 
-            let author = title_split.next()?.trim().to_string();
-            let name = title_split.next()?.trim().to_string();
+            // 页面不完整时可能解析不到 author 或 name，保留下来交给 drop_malformed 统计
+            let author = decode_html(title_split.next().unwrap_or_default().trim());
+            let name = decode_html(title_split.next().unwrap_or_default().trim());
 
             let description = repo
                 .select(&"p.my-1".try_into().unwrap())
                 .next()
-                .map(|e| {
-                    e.text()
-                        .fold(String::new(), |acc, s| acc + s)
-                        .trim()
-                        .to_string()
-                })
+                .map(|e| decode_html(e.text().fold(String::new(), |acc, s| acc + s).trim()))
+                .unwrap_or_default();
+
+            let language = repo
+                .select(&"span[itemprop=\"programmingLanguage\"]".try_into().unwrap())
+                .next()
+                .map(|e| e.text().collect::<String>().trim().to_string())
+                .unwrap_or_default();
+
+            let stars = repo
+                .select(&"a[href$=\"/stargazers\"]".try_into().unwrap())
+                .next()
+                .map(|e| parse_number(&e.text().collect::<String>()))
+                .unwrap_or_default();
+            let stars_today = repo
+                .select(&"span.float-sm-right".try_into().unwrap())
+                .next()
+                .map(|e| parse_number(&e.text().collect::<String>()))
                 .unwrap_or_default();
 
             Some(Repo {
                 author,
                 description,
                 name,
+                language,
+                rank: i + 1,
+                stars,
+                stars_today,
+                stars_delta: stars_today as i64,
+                ..Default::default()
             })
         })
         .collect();
@@ -84,38 +672,774 @@ fn parse_trending(html: String) -> Result<Vec<Repo>> {
     Ok(repos)
 }
 
-pub async fn fetch_repos() -> Result<Vec<Repo>> {
-    let language = env::var("TRENDING_LANGUAGE").unwrap_or("go".to_string());
+fn is_thin_readme(readme: &str, min_length: usize) -> bool {
+    readme.trim().graphemes(true).count() < min_length
+}
+
+// 找出 README 中第一张图片的地址，支持 markdown 图片和 <img> 标签，跳过徽章
+fn first_image_url(readme: &str) -> Option<String> {
+    let mut images = vec![];
+    for (start, _) in readme.match_indices("![") {
+        let rest = &readme[start..];
+        let Some(open) = rest.find("](").map(|i| i + 2) else {
+            continue;
+        };
+        if let Some(close) = rest[open..].find(')').map(|i| i + open) {
+            if let Some(url) = rest[open..close].split_whitespace().next() {
+                images.push((start, url));
+            }
+        }
+    }
+    for (start, _) in readme.match_indices("<img") {
+        let rest = &readme[start..];
+        let Some(src) = rest.find("src=\"").map(|i| i + 5) else {
+            continue;
+        };
+        if let Some(end) = rest[src..].find('"').map(|i| i + src) {
+            images.push((start, &rest[src..end]));
+        }
+    }
+    images.sort_by_key(|(start, _)| *start);
+    images
+        .into_iter()
+        .map(|(_, url)| url)
+        .find(|url| url.starts_with("http") && !url.contains("shields.io") && !url.contains("badge"))
+        .map(String::from)
+}
+
+// 丢弃 author 或 name 为空的项目，返回丢弃的数量
+fn drop_malformed(repos: &mut Vec<Repo>) -> usize {
+    let total = repos.len();
+    repos.retain(|repo| !repo.author.is_empty() && !repo.name.is_empty());
+    total - repos.len()
+}
+
+// 部分描述中的实体被重复转义（例如 &amp;amp;），需要再解码一次
+fn decode_html(text: &str) -> String {
+    html_escape::decode_html_entities(text).to_string()
+}
+
+// 解析 "18,622"、"239 stars today" 之类的文本中的数字
+fn parse_number(text: &str) -> u64 {
+    text.chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or_default()
+}
+
+// 项目和开发者页面共用，kind 为空时是项目页面，developers 为开发者页面
+// 语言和查询参数都经过 URL 编码，例如 c# 编码为 c%23
+pub fn trending_url(config: &TrendingConfig, kind: &str, language: &str, since: &str) -> String {
+    let mut url = url::Url::parse("https://github.com/trending").unwrap();
+    url.path_segments_mut()
+        .unwrap()
+        .extend([kind, language].iter().filter(|part| !part.is_empty()));
+    url.query_pairs_mut().append_pair("since", since);
+    if let Some(spoken_language) = &config.spoken_language {
+        url.query_pairs_mut().append_pair("spoken_language_code", spoken_language);
+    }
+    url.to_string()
+}
+
+async fn fetch_trending(config: &TrendingConfig, language: &str, since: &str) -> Result<Vec<Repo>> {
+    let url = trending_url(config, "", language, since);
+    let max_ratio = config.max_malformed_ratio.unwrap_or(DEFAULT_MAX_MALFORMED_RATIO);
+    let mut repos = parse_trending(fetch_page(config, &url).await?)?;
+    let mut malformed = drop_malformed(&mut repos);
+    // 页面可能只加载了一部分，异常行过多时重新获取一次
+    if malformed as f64 > (repos.len() + malformed) as f64 * max_ratio {
+        warn!("{} malformed repos in {}, fetching again", malformed, url);
+        repos = parse_trending(fetch_page(config, &url).await?)?;
+        malformed = drop_malformed(&mut repos);
+        if malformed as f64 > (repos.len() + malformed) as f64 * max_ratio {
+            return Err(anyhow!("too many malformed repos in {}: {}", url, malformed));
+        }
+    }
+    if malformed > 0 {
+        warn!("dropped {} malformed repos in {}", malformed, url);
+    }
+    for repo in repos.iter_mut() {
+        repo.source_language = language.to_string();
+        repo.since = since.to_string();
+        repo.spoken_language = config.spoken_language.clone().unwrap_or_default();
+        repo.dedup_per_context = config.dedup_per_context;
+    }
+    Ok(repos)
+}
+
+pub async fn fetch_page(config: &TrendingConfig, url: &str) -> Result<String> {
+    let mut user_agent = config.user_agent.clone();
+    if let Some(politeness) = &config.politeness {
+        let (delay, rotated) = {
+            let mut rng = rand::thread_rng();
+            (
+                rng.gen_range(politeness.min_delay..=politeness.max_delay),
+                politeness.user_agents.choose(&mut rng).cloned(),
+            )
+        };
+        if delay > 0 {
+            debug!("waiting {}ms before fetching {}", delay, url);
+            tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        }
+        user_agent = rotated.or(user_agent);
+    }
+
+    let mut req = http::client().get(url);
+    if let Some(user_agent) = &user_agent {
+        req = req.header(reqwest::header::USER_AGENT, user_agent);
+    }
+    if let Some(accept_language) = &config.accept_language {
+        req = req.header(reqwest::header::ACCEPT_LANGUAGE, accept_language);
+    }
+    let max_wait = config.max_rate_limit_wait.unwrap_or(http::DEFAULT_MAX_RATE_LIMIT_WAIT);
+    Ok(http::send_github(req, max_wait).await?.text().await?)
+}
+
+async fn fetch_language(config: &TrendingConfig, language: &str) -> Result<Vec<Repo>> {
     info!("fetching {} repos...", language);
 
-    let url = format!("https://github.com/trending/{}?since=daily", language);
-    let resp = reqwest::get(&url)
-        .await?
-        .text()
-        .await?;
-    parse_trending(resp)
+    let windows: &[&str] = if config.widen_on_empty {
+        &["daily", "weekly", "monthly"]
+    } else {
+        &["daily"]
+    };
+    for since in windows {
+        let mut repos = fetch_trending(config, language, since).await?;
+        if !repos.is_empty() {
+            info!("fetched {} {} repos with since={}", repos.len(), language, since);
+            if config.canonicalize_repos {
+                for repo in repos.iter_mut() {
+                    if let Err(e) = repo.canonicalize().await {
+                        warn!("canonicalize {}/{} failed: {:#}", repo.author, repo.name, e);
+                    }
+                }
+            }
+            return Ok(repos);
+        }
+        info!("no {} repos trending with since={}", language, since);
+    }
+    Ok(vec![])
+}
+
+// 并发获取所有语言的项目，部分语言失败时只记录日志
+pub async fn fetch_repos(config: &TrendingConfig) -> Result<Vec<Repo>> {
+    if let (TrendingMode::Search, Some(search)) = (config.mode, &config.search) {
+        return fetch_search(config, search).await;
+    }
+    let languages = config.languages();
+    let mut results: Vec<(usize, Result<Vec<Repo>>)> = stream::iter(languages.clone().into_iter().enumerate())
+        .map(|(i, language)| async move { (i, fetch_language(config, &language).await) })
+        .buffer_unordered(config.fetch_concurrency.unwrap_or(DEFAULT_FETCH_CONCURRENCY).max(1))
+        .collect()
+        .await;
+    // 按配置中的语言顺序合并
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut lists = vec![];
+    let mut last_error = None;
+    for (i, result) in results {
+        match result {
+            Ok(repos) => lists.push(repos),
+            Err(e) => {
+                error!("fetch {} repos failed: {:#}", languages[i], e);
+                last_error = Some(e);
+            }
+        }
+    }
+    if lists.is_empty() {
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+    }
+
+    let mut repos: Vec<Repo> = if config.interleave_languages {
+        interleave(lists)
+    } else {
+        lists.into_iter().flatten().collect()
+    };
+    if !config.language_priority.is_empty() {
+        sort_by_language_priority(&mut repos, &config.language_priority);
+    }
+    // 同一个项目可能出现在多个语言的列表中，开启 dedup_per_context 时各自保留
+    let mut seen = HashSet::new();
+    Ok(repos.into_iter().filter(|repo| seen.insert(repo.post_id())).collect())
+}
+
+// 按 language_priority 的顺序排列各语言，未列出的语言排在最后，同一语言内保持原有顺序
+fn sort_by_language_priority(repos: &mut [Repo], priority: &[String]) {
+    repos.sort_by_key(|repo| {
+        priority
+            .iter()
+            .position(|language| language.eq_ignore_ascii_case(&repo.source_language))
+            .unwrap_or(priority.len())
+    });
+}
+
+// 已经过 URL 编码的搜索条件，c++、c# 等语言名中的符号需要编码
+fn replay_query(from: time::Date, date: time::Date, language: &str) -> String {
+    let mut query = format!("created:{}..{} pushed:>={}", from, date, date);
+    if !language.is_empty() {
+        query = format!("{} language:{}", query, language);
+    }
+    url::form_urlencoded::byte_serialize(query.as_bytes()).collect()
+}
+
+// GitHub 没有历史 trending，用搜索接口近似：该日期前一周内创建、之后仍有提交的项目按 star 排序
+pub async fn fetch_replay(config: &TrendingConfig, date: time::Date) -> Result<Vec<Repo>> {
+    let from = date - time::Duration::days(7);
+    let mut lists = vec![];
+    for language in config.languages() {
+        let query = replay_query(from, date, &language);
+        let resp = github::get(&format!("/search/repositories?q={}&sort=stars&order=desc&per_page=25", query))
+            .await
+            .context(format!("While searching {} repos", language))?;
+        let repos = parse_search(&resp, &language, &format!("replay {}", date), config.dedup_per_context);
+        info!("replayed {} {} repos on {}", repos.len(), language, date);
+        lists.push(repos);
+    }
+    let repos = if config.interleave_languages {
+        interleave(lists)
+    } else {
+        lists.into_iter().flatten().collect()
+    };
+    let mut seen = HashSet::new();
+    Ok(repos.into_iter().filter(|repo| seen.insert(repo.post_id())).collect())
+}
+
+// 将搜索接口返回的项目转换为 Repo，排名按搜索结果的顺序
+fn parse_search(resp: &serde_json::Value, source_language: &str, since: &str, dedup_per_context: bool) -> Vec<Repo> {
+    let items = resp["items"].as_array().cloned().unwrap_or_default();
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            Some(Repo {
+                author: item["owner"]["login"].as_str()?.to_string(),
+                name: item["name"].as_str()?.to_string(),
+                description: item["description"].as_str().unwrap_or_default().to_string(),
+                language: item["language"].as_str().unwrap_or_default().to_string(),
+                source_language: source_language.to_string(),
+                since: since.to_string(),
+                rank: i + 1,
+                stars: item["stargazers_count"].as_u64().unwrap_or_default(),
+                dedup_per_context,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+// trending.mode = "search" 时按配置的查询条件获取项目，代替 trending 页面
+async fn fetch_search(config: &TrendingConfig, search: &SearchConfig) -> Result<Vec<Repo>> {
+    let query: String = url::form_urlencoded::byte_serialize(search.query.as_bytes()).collect();
+    let resp = github::get(&format!(
+        "/search/repositories?q={}&sort={}&order={}&per_page={}",
+        query,
+        search.sort,
+        search.order,
+        search.per_page.clamp(1, 100)
+    ))
+    .await
+    .context(format!("While searching {}", search.query))?;
+    let repos = parse_search(&resp, "", "search", config.dedup_per_context);
+    info!("found {} repos matching {}", repos.len(), search.query);
+    Ok(repos)
+}
+
+// 轮流从各语言的列表中取项目，避免某个语言占据推送队列的前面
+fn interleave(lists: Vec<Vec<Repo>>) -> Vec<Repo> {
+    let mut iters: Vec<_> = lists.into_iter().map(|list| list.into_iter()).collect();
+    let mut result = vec![];
+    loop {
+        let before = result.len();
+        result.extend(iters.iter_mut().filter_map(|iter| iter.next()));
+        if result.len() == before {
+            return result;
+        }
+    }
+}
+
+// 抓取线上的 trending 页面，检查解析规则是否仍然匹配 GitHub 当前的页面结构
+pub async fn self_test() -> Result<()> {
+    const MIN_REPOS: usize = 5;
+
+    let repos = fetch_trending(&TrendingConfig::default(), "", "daily").await?;
+    if repos.len() < MIN_REPOS {
+        return Err(anyhow!("self test failed: only {} repos parsed, expected at least {}", repos.len(), MIN_REPOS));
+    }
+    if let Some(repo) = repos.iter().find(|repo| repo.author.is_empty() || repo.name.is_empty()) {
+        return Err(anyhow!("self test failed: repo #{} has empty author or name", repo.rank));
+    }
+    info!("self test passed, parsed {} repos", repos.len());
+    Ok(())
 }
 
 #[inline]
-fn now_ts() -> u64 {
+pub fn now_ts() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
 }
 
-pub async fn mark_posted_repo(
+// 记录每个项目的 star 数，计算与上次获取时的增量
+// 返回有历史记录的项目数量，没有记录时 seed_interval 不为空则按 stars_today 估算这段时间的增量
+pub async fn track_stars(conn: &mut redis::aio::Connection, repos: &mut [Repo], seed_interval: Option<u64>) -> Result<usize> {
+    let mut tracked = 0;
+    for repo in repos.iter_mut() {
+        let key = format!("stars:{}/{}", repo.author, repo.name);
+        let previous: Option<u64> = conn.getset(&key, repo.stars).await?;
+        conn.expire::<_, ()>(&key, STARS_TTL).await?;
+        match (previous, seed_interval) {
+            (Some(previous), _) => {
+                repo.stars_delta = repo.stars as i64 - previous as i64;
+                tracked += 1;
+            }
+            (None, Some(interval)) => repo.stars_delta = seeded_delta(repo.stars_today, interval),
+            (None, None) => {}
+        }
+    }
+    Ok(tracked)
+}
+
+// 假设一天内均匀增长，interval 秒内的增量约为 stars_today * interval / 86400
+fn seeded_delta(stars_today: u64, interval: u64) -> i64 {
+    (stars_today as f64 * interval.min(86400) as f64 / 86400.0).round() as i64
+}
+
+// 按 GitHub API 返回的项目信息过滤，请求失败时保留项目
+pub async fn filter_by_info(
+    repos: Vec<Repo>,
+    filter: &FilterConfig,
     conn: &mut redis::aio::Connection,
-    repo: &Repo,
     ttl: usize,
+) -> Vec<Repo> {
+    let mut result = Vec::with_capacity(repos.len());
+    for mut repo in repos {
+        if filter.needs_metadata() {
+            match repo.metadata(conn, ttl).await {
+                Ok(metadata) => {
+                    if let Some(reason) = filter.rejects(&metadata) {
+                        info!("skip repo {}: {}", repo.id(), reason);
+                        continue;
+                    }
+                }
+                Err(e) => warn!("get repo info {} failed, keep it: {:#}", repo.id(), e),
+            }
+        }
+        if filter.require_recent_release.is_some() {
+            match repo.release(conn, ttl).await {
+                Ok(release) => {
+                    if let Some(reason) = filter.rejects_release(release.as_ref()) {
+                        info!("skip repo {}: {}", repo.id(), reason);
+                        continue;
+                    }
+                    if let Some(release) = release {
+                        repo.set_release(&release);
+                    }
+                }
+                Err(e) => warn!("get latest release of {} failed, keep it: {:#}", repo.id(), e),
+            }
+        }
+        result.push(repo);
+    }
+    result
+}
+
+// 按 stars_today 加权随机排序（Efraimidis-Spirakis 算法），stars_today 越高越可能排在前面
+pub fn weighted_shuffle(repos: &mut Vec<Repo>, config: &SamplingConfig) {
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut keyed: Vec<(f64, Repo)> = repos
+        .drain(..)
+        .map(|repo| {
+            let weight = ((repo.stars_today + 1) as f64).powf(1.0 - config.randomness);
+            let key = rng.gen::<f64>().powf(1.0 / weight);
+            (key, repo)
+        })
+        .collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+    repos.extend(keyed.into_iter().map(|(_, repo)| repo));
+}
+
+// GitHub 接口的结果缓存在 Redis 中，与 metadata 使用相同的过期时间
+async fn cached<T, F, Fut>(conn: &mut redis::aio::Connection, key: &str, ttl: usize, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let value: Option<String> = conn.get(key).await?;
+    if let Some(value) = value.and_then(|value| serde_json::from_str(&value).ok()) {
+        return Ok(value);
+    }
+    let value = fetch().await?;
+    conn.set_ex::<_, _, ()>(key, serde_json::to_string(&value)?, ttl).await?;
+    Ok(value)
+}
+
+// 同一份 trending 列表对应同一个进度 key，用于崩溃重启后跳过已处理的项目
+pub fn progress_key(repos: &[Repo]) -> String {
+    let ids: Vec<String> = repos.iter().map(Repo::id).collect();
+    format!("progress:{}", stable_hash(&ids.join("\n")))
+}
+
+// 保存在 Redis 中的 key 需要在重启和升级工具链后保持不变，不能使用 DefaultHasher
+fn stable_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn processed_ids(conn: &mut redis::aio::Connection, key: &str) -> Result<HashSet<String>> {
+    Ok(conn.smembers(key).await?)
+}
+
+pub async fn mark_processed(conn: &mut redis::aio::Connection, key: &str, id: &str, ttl: usize) -> Result<()> {
+    conn.sadd::<_, _, ()>(key, id).await?;
+    conn.expire::<_, ()>(key, ttl).await?;
+    Ok(())
+}
+
+pub fn posted_key(platform: &str, id: &str) -> String {
+    format!("{}:{}", platform, id)
+}
+
+// 推送记录，保存在去重 key 的值中，供 stats 子命令统计
+#[derive(Serialize, Deserialize)]
+pub struct PostRecord {
+    pub ts: u64,
+    pub platform: String,
+    pub language: String,
+    // 平台返回的消息 id，供置顶、编辑、删除使用
+    #[serde(default)]
+    pub message_id: Option<String>,
+    // 推送时的 star 数，用于判断再次上榜时是否需要修改原消息
+    #[serde(default)]
+    pub stars: Option<u64>,
+}
+
+pub async fn mark_posted(
+    conn: &mut redis::aio::Connection,
+    platform: &str,
+    id: &str,
+    language: &str,
+    message_id: Option<&str>,
+    stars: Option<u64>,
+    ttl: usize,
+) -> Result<()> {
+    let record = PostRecord {
+        ts: now_ts(),
+        platform: platform.to_string(),
+        language: language.to_string(),
+        message_id: message_id.map(str::to_string),
+        stars,
+    };
+    conn.set_ex::<_, _, ()>(posted_key(platform, id), serde_json::to_string(&record)?, ttl)
+        .await?;
+    Ok(())
+}
+
+// 该平台是否有任何推送记录
+pub async fn has_posts(conn: &mut redis::aio::Connection, platform: &str) -> Result<bool> {
+    let mut iter = conn.scan_match::<_, String>(posted_key(platform, "*")).await?;
+    Ok(iter.next_item().await.is_some())
+}
+
+pub async fn is_posted(pool: &mut RedisPool, platform: &str, id: &str) -> Result<bool> {
+    pool.exists(&posted_key(platform, id)).await
+}
+
+pub async fn get_post_record(pool: &mut RedisPool, platform: &str, id: &str) -> Result<Option<PostRecord>> {
+    match pool.get(&posted_key(platform, id)).await? {
+        // 旧版本只保存了时间戳，没有消息 id
+        Some(value) => Ok(serde_json::from_str(&value).ok()),
+        None => Ok(None),
+    }
+}
+
+// 更新推送记录，保留原有过期时间
+pub async fn update_post_record(
+    conn: &mut redis::aio::Connection,
+    platform: &str,
+    id: &str,
+    record: &PostRecord,
 ) -> Result<()> {
-    conn.set_ex(format!("{}/{}", repo.author, repo.name), now_ts(), ttl)
+    redis::cmd("SET")
+        .arg(posted_key(platform, id))
+        .arg(serde_json::to_string(record)?)
+        .arg("KEEPTTL")
+        .query_async::<_, ()>(conn)
         .await?;
     Ok(())
 }
 
-pub async fn is_repo_posted(conn: &mut redis::aio::Connection, repo: &Repo) -> Result<bool> {
-    Ok(conn
-        .exists(format!("{}/{}", repo.author, repo.name))
-        .await?)
+// 定时推送和人工审核的内容在入队时已标记，实际发出后再补上消息 id
+pub async fn set_message_id(pool: &mut RedisPool, platform: &str, id: &str, message_id: &str) -> Result<()> {
+    let Some(mut record) = get_post_record(pool, platform, id).await? else {
+        return Ok(());
+    };
+    record.message_id = Some(message_id.to_string());
+    update_post_record(pool.writer(), platform, id, &record).await
+}
+
+// 检查其他机器人共享的去重记录
+pub async fn is_posted_externally(pool: &mut RedisPool, dedups: &[ExternalDedup], repo: &Repo) -> Result<bool> {
+    let id = repo.id();
+    let vars = [("id", id.as_str()), ("author", repo.author.as_str()), ("name", repo.name.as_str())];
+    for dedup in dedups {
+        let posted = match (&dedup.key, &dedup.set) {
+            (Some(key), _) => pool.exists(&format::render(key, &vars)).await?,
+            (None, Some(set)) => pool.is_member(&format::render(set, &vars), &id).await?,
+            (None, None) => false,
+        };
+        if posted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// 忽略大小写、标点和空白后的描述哈希，用于识别描述相同的 fork 或改名项目
+fn description_key(description: &str) -> Option<String> {
+    let normalized: String = description
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if normalized.is_empty() {
+        return None;
+    }
+    Some(format!("description:{}", stable_hash(&normalized)))
+}
+
+// 最近是否推送过描述相同的其他项目
+pub async fn is_description_posted(pool: &mut RedisPool, repo: &Repo) -> Result<bool> {
+    let Some(key) = description_key(&repo.description) else {
+        return Ok(false);
+    };
+    let id: Option<String> = pool.get(&key).await?;
+    Ok(id.is_some_and(|id| id != repo.id()))
+}
+
+pub async fn mark_description_posted(conn: &mut redis::aio::Connection, repo: &Repo, ttl: usize) -> Result<()> {
+    if let Some(key) = description_key(&repo.description) {
+        conn.set_ex::<_, _, ()>(key, repo.id(), ttl).await?;
+    }
+    Ok(())
+}
+
+fn pinned_key(platform: &str) -> String {
+    format!("pinned:{}", platform)
+}
+
+// 当前置顶的消息 id
+pub async fn get_pinned(conn: &mut redis::aio::Connection, platform: &str) -> Result<Option<String>> {
+    Ok(conn.get(pinned_key(platform)).await?)
+}
+
+pub async fn set_pinned(conn: &mut redis::aio::Connection, platform: &str, message_id: &str) -> Result<()> {
+    conn.set::<_, _, ()>(pinned_key(platform), message_id).await?;
+    Ok(())
+}
+
+fn topic_key(platform: &str, id: &str) -> String {
+    format!("topic:{}:{}", platform, id)
+}
+
+// 首次推送的消息 id，在 reply_on_retrend 时间内再次上榜时回复到这条消息下
+pub async fn get_topic(pool: &mut RedisPool, platform: &str, id: &str) -> Result<Option<String>> {
+    pool.get(&topic_key(platform, id)).await
+}
+
+pub async fn set_topic(conn: &mut redis::aio::Connection, platform: &str, id: &str, message_id: &str, ttl: usize) -> Result<()> {
+    conn.set_ex::<_, _, ()>(topic_key(platform, id), message_id, ttl).await?;
+    Ok(())
+}
+
+pub async fn forget_topic(conn: &mut redis::aio::Connection, platform: &str, id: &str) -> Result<()> {
+    conn.del::<_, ()>(topic_key(platform, id)).await?;
+    Ok(())
+}
+
+fn messages_key(platform: &str) -> String {
+    format!("messages:{}", platform)
+}
+
+// 记录消息 id 和推送时间，供 delete_after 清理过期消息
+pub async fn remember_message(conn: &mut redis::aio::Connection, platform: &str, message_id: &str) -> Result<()> {
+    conn.zadd::<_, _, _, ()>(messages_key(platform), message_id, now_ts()).await?;
+    Ok(())
+}
+
+// 推送时间早于 before 的消息 id
+pub async fn stale_messages(conn: &mut redis::aio::Connection, platform: &str, before: u64) -> Result<Vec<String>> {
+    Ok(conn.zrangebyscore(messages_key(platform), 0, before).await?)
+}
+
+pub async fn forget_message(conn: &mut redis::aio::Connection, platform: &str, message_id: &str) -> Result<()> {
+    conn.zrem::<_, _, ()>(messages_key(platform), message_id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        drop_malformed, first_image_url, is_thin_readme, parse_search, replay_query, stable_hash, parse_trending, seeded_delta, sort_by_language_priority, truncate, Repo,
+        StructuredSummary,
+    };
+    use crate::platform::format::grapheme_count;
+
+    #[test]
+    fn test_replay_query_encodes_language() {
+        let date = time::macros::date!(2024 - 01 - 08);
+        let from = time::macros::date!(2024 - 01 - 01);
+        assert_eq!(
+            replay_query(from, date, "c++"),
+            "created%3A2024-01-01..2024-01-08+pushed%3A%3E%3D2024-01-08+language%3Ac%2B%2B"
+        );
+        assert!(replay_query(from, date, "c#").ends_with("language%3Ac%23"));
+    }
+
+    #[test]
+    fn test_trending_url() {
+        let config = crate::config::TrendingConfig {
+            spoken_language: Some("zh&x".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            super::trending_url(&config, "", "c#", "daily"),
+            "https://github.com/trending/c%23?since=daily&spoken_language_code=zh%26x"
+        );
+        assert_eq!(
+            super::trending_url(&crate::config::TrendingConfig::default(), "developers", "", "weekly"),
+            "https://github.com/trending/developers?since=weekly"
+        );
+    }
+
+    #[test]
+    fn test_stable_hash() {
+        assert_eq!(stable_hash("servo/servo"), stable_hash("servo/servo"));
+        assert_eq!(stable_hash(""), "e3b0c44298fc1c14");
+    }
+
+    #[test]
+    fn test_is_thin_readme() {
+        assert!(is_thin_readme("  # demo\n\n  ", 10));
+        assert!(!is_thin_readme("# demo\n\n一个浏览器引擎", 10));
+    }
+
+    #[test]
+    fn test_parse_trending() {
+        let html = std::fs::read_to_string("testdata/test.html").unwrap();
+        let repos = parse_trending(html).unwrap();
+        assert_eq!(repos.len(), 25);
+        assert_eq!(repos[0].author, "servo");
+        assert_eq!(repos[0].name, "servo");
+        assert_eq!(repos[0].description, "The Servo Browser Engine");
+    }
+
+    #[test]
+    fn test_parse_trending_decodes_entities() {
+        let html = std::fs::read_to_string("testdata/entities.html").unwrap();
+        let repos = parse_trending(html).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].author, "rust-lang");
+        assert_eq!(repos[0].name, "rust-clippy");
+        assert_eq!(
+            repos[0].description,
+            "A bunch of lints to catch common mistakes & improve your Rust code. Book: https://doc.rust-lang.org/clippy/ 'lints' <3"
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_page() {
+        let html = std::fs::read_to_string("testdata/truncated.html").unwrap();
+        let mut repos = parse_trending(html).unwrap();
+        assert_eq!(repos.len(), 3);
+        assert_eq!(drop_malformed(&mut repos), 1);
+        assert_eq!(repos.len(), 2);
+        assert_eq!(repos[0].id(), "servo/servo");
+    }
+
+    #[test]
+    fn test_first_image_url_skips_badges() {
+        let readme = "# demo\n![stars](https://img.shields.io/github/stars/a/b)\n<img src=\"https://example.com/demo.gif\" width=\"600\">\n![shot](https://example.com/shot.png)";
+        assert_eq!(first_image_url(readme).as_deref(), Some("https://example.com/demo.gif"));
+        assert_eq!(first_image_url("no images here"), None);
+    }
+
+    #[test]
+    fn test_truncate_cjk_by_bytes() {
+        let summary = "这是一个很长的中文项目简介".repeat(300);
+        let truncated = truncate(&summary, 10000, str::len);
+        assert!(truncated.len() <= 10000);
+        assert!(truncated.len() > 10000 - 3);
+        assert!(truncated.ends_with(" ..."));
+        // 按字素计算时 10000 个字素足够放下全部内容
+        assert_eq!(truncate(&summary, 10000, grapheme_count), summary);
+    }
+
+    #[test]
+    fn test_why_trending_context() {
+        let repo = Repo {
+            author: "servo".to_string(),
+            name: "servo".to_string(),
+            description: "A browser engine".to_string(),
+            stars: 30000,
+            stars_today: 420,
+            ..Default::default()
+        };
+        let commits = vec!["2024-05-01T00:00:00Z Enable WebGPU by default".to_string()];
+        assert_eq!(
+            repo.why_trending_context(&commits, Some("2024-04-30T00:00:00Z v0.1.0")),
+            "项目：servo/servo\n描述：A browser engine\n总 star 数：30000\n今日新增 star：420\n\
+             最近的提交：\n2024-05-01T00:00:00Z Enable WebGPU by default\n最新 release：2024-04-30T00:00:00Z v0.1.0"
+        );
+        assert!(!repo.why_trending_context(&[], None).contains("最近的提交"));
+    }
+
+    #[test]
+    fn test_parse_search() {
+        let resp = serde_json::json!({"items": [
+            {"owner": {"login": "servo"}, "name": "servo", "description": null, "language": "Rust", "stargazers_count": 30000},
+            {"owner": {}, "name": "broken"},
+            {"owner": {"login": "tokio-rs"}, "name": "tokio", "description": "Async runtime", "stargazers_count": 28000},
+        ]});
+        let repos = parse_search(&resp, "", "search", false);
+        assert_eq!(repos.len(), 2);
+        assert_eq!((repos[0].id(), repos[0].description.as_str(), repos[0].stars), ("servo/servo".to_string(), "", 30000));
+        assert_eq!((repos[1].id(), repos[1].rank), ("tokio-rs/tokio".to_string(), 3));
+    }
+
+    #[test]
+    fn test_seeded_delta() {
+        assert_eq!(seeded_delta(480, 3600), 20);
+        assert_eq!(seeded_delta(480, 7 * 86400), 480);
+        assert_eq!(seeded_delta(0, 3600), 0);
+    }
+
+    #[test]
+    fn test_sort_by_language_priority() {
+        let mut repos = parse_trending(std::fs::read_to_string("testdata/test.html").unwrap()).unwrap();
+        repos.truncate(5);
+        for (repo, language) in repos.iter_mut().zip(["python", "go", "rust", "python", "rust"]) {
+            repo.source_language = language.to_string();
+        }
+        let ranks = |repos: &[super::Repo]| repos.iter().map(|repo| repo.rank).collect::<Vec<_>>();
+        sort_by_language_priority(&mut repos, &["rust".to_string(), "Python".to_string()]);
+        assert_eq!(ranks(&repos), vec![3, 5, 1, 4, 2]);
+    }
+
+    #[test]
+    fn test_render_structured_summary() {
+        let output = r#"{"title": "Servo", "one_liner": "用 Rust 编写的浏览器引擎", "features": ["并行渲染", "可嵌入"]}"#;
+        let summary: StructuredSummary = serde_json::from_str(output).unwrap();
+        assert_eq!(
+            summary.render("{title}：{one_liner}\n\n{features}"),
+            "Servo：用 Rust 编写的浏览器引擎\n\n- 并行渲染\n- 可嵌入"
+        );
+        assert!(serde_json::from_str::<StructuredSummary>("Servo 是一个浏览器引擎").is_err());
+    }
 }
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveTime};
+use chrono_tz::Tz;
+use redis::{AsyncCommands, Direction};
+use serde::{Deserialize, Serialize};
+use crate::config::ScheduleConfig;
+use crate::platform::types::RepoContent;
+use crate::repo::Repo;
+
+// 等待推送、定时推送或审核的内容，摘要在生成内容时已经得到
+#[derive(Serialize, Deserialize)]
+pub struct PendingPost {
+    pub platform: String,
+    pub id: String,
+    pub content: String,
+    // 渲染前的简介，用于推送时的相似度检查
+    #[serde(default)]
+    pub summary: Option<String>,
+    // 生成简介时消耗的 token 数，推送时记录在审计日志中
+    #[serde(default)]
+    pub token_usage: u64,
+    // 生成内容时项目的 star 数，推送后记录到周报中
+    #[serde(default)]
+    pub stars: Option<u64>,
+}
+
+impl PendingPost {
+    pub fn new(platform: &str, repo: &Repo, content: RepoContent) -> Self {
+        PendingPost {
+            platform: platform.to_string(),
+            id: repo.post_id(),
+            content: content.content,
+            summary: Some(content.summary),
+            token_usage: content.token_usage,
+            stars: Some(repo.stars),
+        }
+    }
+}
+
+pub async fn enqueue(conn: &mut redis::aio::Connection, config: &ScheduleConfig, post: &PendingPost) -> Result<()> {
+    conn.rpush::<_, _, ()>(&config.key, serde_json::to_string(post)?).await?;
+    Ok(())
+}
+
+// 最近一个已经到达的推送时间，例如 "2024-01-01 09:00"
+fn latest_slot(times: &[NaiveTime], now: DateTime<Tz>) -> Option<String> {
+    let today = times.iter().filter(|time| **time <= now.time()).max();
+    let (date, time) = match today {
+        Some(time) => (now.date_naive(), time),
+        None => (now.date_naive() - Duration::days(1), times.iter().max()?),
+    };
+    Some(format!("{} {}", date, time.format("%H:%M")))
+}
+
+// 从队列中取出的内容，raw 为队列中的原始值，推送结束前保留在处理中列表
+pub struct DuePost {
+    pub raw: String,
+    pub post: PendingPost,
+}
+
+fn processing_key(config: &ScheduleConfig) -> String {
+    format!("{}:processing", config.key)
+}
+
+// 推送结束后从处理中列表删除
+pub async fn done(conn: &mut redis::aio::Connection, config: &ScheduleConfig, due: &DuePost) -> Result<()> {
+    conn.lrem::<_, _, ()>(processing_key(config), 1, &due.raw).await?;
+    Ok(())
+}
+
+// 推送失败的内容放回队列头部，保持原有顺序，在下一个推送时间重试
+pub async fn requeue(conn: &mut redis::aio::Connection, config: &ScheduleConfig, posts: &[DuePost]) -> Result<()> {
+    for due in posts.iter().rev() {
+        conn.lpush::<_, _, ()>(&config.key, &due.raw).await?;
+        done(conn, config, due).await?;
+    }
+    Ok(())
+}
+
+// 上次退出时没有推送完的内容放回队列头部，保持原有顺序
+async fn recover(conn: &mut redis::aio::Connection, config: &ScheduleConfig) -> Result<()> {
+    loop {
+        let item: Option<String> = conn
+            .lmove(processing_key(config), config.key.clone(), Direction::Right, Direction::Left)
+            .await?;
+        if item.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+// 首次启动时没有记录，只记下当前的推送时间，等到下一个推送时间再释放，避免启动后立即推送
+fn is_new_slot(last: Option<&str>, slot: &str) -> bool {
+    last.is_some_and(|last| last != slot)
+}
+
+// 到达新的推送时间时，从队列头部取出 per_slot 条内容；已释放的时间保存在 Redis 中，重启后不会重复释放
+// 取出的内容先移到处理中列表，推送结束后再删除，中途退出时下次重新放回队列
+pub async fn take_due(
+    conn: &mut redis::aio::Connection,
+    config: &ScheduleConfig,
+    now: DateTime<Tz>,
+) -> Result<Vec<DuePost>> {
+    recover(conn, config).await?;
+    let Some(slot) = latest_slot(&config.parsed_times(), now) else {
+        return Ok(vec![]);
+    };
+    let last_key = format!("{}:last_slot", config.key);
+    let last: Option<String> = conn.get(&last_key).await?;
+    if last.as_deref() == Some(slot.as_str()) {
+        return Ok(vec![]);
+    }
+    conn.set::<_, _, ()>(&last_key, &slot).await?;
+    if !is_new_slot(last.as_deref(), &slot) {
+        return Ok(vec![]);
+    }
+
+    let mut posts = vec![];
+    for _ in 0..config.per_slot {
+        let raw: Option<String> = conn
+            .lmove(config.key.clone(), processing_key(config), Direction::Left, Direction::Right)
+            .await?;
+        let Some(raw) = raw else {
+            break;
+        };
+        let post = serde_json::from_str(&raw)?;
+        posts.push(DuePost { raw, post });
+    }
+    Ok(posts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_new_slot, latest_slot};
+    use chrono::{NaiveTime, TimeZone};
+
+    #[test]
+    fn test_first_start_waits_for_next_slot() {
+        let times = [NaiveTime::from_hms_opt(9, 0, 0).unwrap(), NaiveTime::from_hms_opt(18, 0, 0).unwrap()];
+        let now = chrono_tz::Asia::Shanghai.with_ymd_and_hms(2024, 1, 2, 8, 0, 0).unwrap();
+        let slot = latest_slot(&times, now).unwrap();
+        assert_eq!(slot, "2024-01-01 18:00");
+        assert!(!is_new_slot(None, &slot));
+        assert!(!is_new_slot(Some("2024-01-01 18:00"), &slot));
+        assert!(is_new_slot(Some("2024-01-01 09:00"), &slot));
+    }
+}
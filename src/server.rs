@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+
+// 请求行和请求头的最大长度，不读取请求体
+const MAX_HEADER_SIZE: u64 = 8192;
+
+// 可选的 HTTP 服务，用于从外部手动触发一轮推送，修改后需要重启才会生效
+#[derive(Deserialize, Clone)]
+pub struct ServerConfig {
+    // 监听地址，例如 127.0.0.1:8080
+    pub listen: String,
+    // 请求需要携带 Authorization: Bearer <token>
+    pub token: String,
+}
+
+// 一轮推送的结果，skipped 包括被过滤、已推送过和留到下一轮的项目
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct CycleReport {
+    pub fetched: usize,
+    pub posted: usize,
+    pub skipped: usize,
+    // skipped 中命中 denylist、已推送过（包括其他机器人和相同描述）、被 filter 或内容审核过滤的数量
+    pub denied: usize,
+    pub deduped: usize,
+    pub filtered: usize,
+    // 生成内容或推送失败的次数，本轮出错时另加一次
+    pub errors: usize,
+    pub error: Option<String>,
+}
+
+impl CycleReport {
+    pub fn finish(&self, error: Option<String>) -> CycleReport {
+        CycleReport {
+            skipped: self.fetched.saturating_sub(self.posted),
+            errors: self.errors + usize::from(error.is_some()),
+            error,
+            ..self.clone()
+        }
+    }
+
+    // 发送到 cycle_report 平台的摘要，需要先调用 finish
+    pub fn digest(&self) -> String {
+        let mut digest = format!(
+            "Fetched {}, posted {}, skipped {} (deny:{}, dedup:{}, filter:{}), {} errors",
+            self.fetched, self.posted, self.skipped, self.denied, self.deduped, self.filtered, self.errors
+        );
+        if let Some(error) = &self.error {
+            digest.push_str(&format!("\n{}", error));
+        }
+        digest
+    }
+}
+
+// 主循环收到后执行一轮推送，并通过 oneshot 返回结果
+pub type Trigger = oneshot::Sender<CycleReport>;
+
+pub async fn serve(config: ServerConfig, triggers: mpsc::Sender<Trigger>) -> Result<()> {
+    let listener = TcpListener::bind(&config.listen)
+        .await
+        .context(format!("While listening on {}", config.listen))?;
+    info!("http server listening on {}", config.listen);
+    loop {
+        let (stream, addr) = listener.accept().await.context("While accepting connection")?;
+        let token = config.token.clone();
+        let triggers = triggers.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &token, &triggers).await {
+                warn!("http request from {} failed: {:#}", addr, e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, token: &str, triggers: &mpsc::Sender<Trigger>) -> Result<()> {
+    let mut reader = BufReader::new((&mut stream).take(MAX_HEADER_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let (status, body) = match route(&request_line, authorization.as_deref(), token) {
+        Err((status, message)) => (status, serde_json::json!({ "error": message }).to_string()),
+        Ok(()) => trigger(triggers).await,
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+// 只接受带正确 token 的 POST /trigger
+fn route(request_line: &str, authorization: Option<&str>, token: &str) -> Result<(), (&'static str, &'static str)> {
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    if path != "/trigger" {
+        return Err(("404 Not Found", "not found"));
+    }
+    if method != "POST" {
+        return Err(("405 Method Not Allowed", "method not allowed"));
+    }
+    let provided = authorization.and_then(|value| value.strip_prefix("Bearer ")).unwrap_or_default();
+    if !constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        return Err(("401 Unauthorized", "invalid token"));
+    }
+    Ok(())
+}
+
+// 逐字节比较全部内容，避免通过响应时间猜测 token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// 已经有一次触发在排队时直接返回，避免重复推送
+async fn trigger(triggers: &mpsc::Sender<Trigger>) -> (&'static str, String) {
+    let (reply, report) = oneshot::channel();
+    match triggers.try_send(reply) {
+        Ok(()) => {}
+        Err(TrySendError::Full(_)) => {
+            return ("409 Conflict", serde_json::json!({ "error": "a triggered cycle is already pending" }).to_string());
+        }
+        Err(TrySendError::Closed(_)) => {
+            return ("503 Service Unavailable", serde_json::json!({ "error": "shutting down" }).to_string());
+        }
+    }
+    match report.await {
+        Ok(report) => ("200 OK", serde_json::to_string(&report).unwrap_or_default()),
+        Err(_) => ("500 Internal Server Error", serde_json::json!({ "error": "cycle was cancelled" }).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{route, CycleReport};
+
+    #[test]
+    fn test_cycle_digest() {
+        let cycle = CycleReport {
+            fetched: 25,
+            posted: 4,
+            denied: 2,
+            deduped: 15,
+            filtered: 1,
+            errors: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            cycle.finish(None).digest(),
+            "Fetched 25, posted 4, skipped 21 (deny:2, dedup:15, filter:1), 1 errors"
+        );
+        assert!(cycle.finish(Some("boom".to_string())).digest().ends_with("2 errors\nboom"));
+    }
+
+    #[test]
+    fn test_route_requires_token() {
+        let line = "POST /trigger HTTP/1.1\r\n";
+        assert!(route(line, Some("Bearer secret"), "secret").is_ok());
+        assert_eq!(route(line, Some("Bearer wrong"), "secret").unwrap_err().0, "401 Unauthorized");
+        assert_eq!(route(line, None, "secret").unwrap_err().0, "401 Unauthorized");
+        assert_eq!(route("GET /trigger HTTP/1.1\r\n", Some("Bearer secret"), "secret").unwrap_err().0, "405 Method Not Allowed");
+        assert_eq!(route("POST / HTTP/1.1\r\n", Some("Bearer secret"), "secret").unwrap_err().0, "404 Not Found");
+    }
+}
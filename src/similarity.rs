@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use crate::config::SimilarityConfig;
+
+// 最近推送过的简介，记录项目 id，避免同一项目推送到多个平台时与自己比较
+#[derive(Serialize, Deserialize)]
+struct Recent {
+    id: String,
+    summary: String,
+}
+
+// 按字符切分为 3-gram，兼容中文等没有空格分词的内容
+fn shingles(content: &str) -> HashSet<String> {
+    let chars: Vec<char> = content
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+// 与最近推送过的其他项目的简介比较，返回最高的相似度
+pub async fn max_similarity(
+    conn: &mut redis::aio::Connection,
+    config: &SimilarityConfig,
+    id: &str,
+    summary: &str,
+) -> Result<f64> {
+    let recent: Vec<String> = conn.lrange(&config.key, 0, config.window as isize - 1).await?;
+    Ok(score(&recent, id, summary))
+}
+
+// 旧版本保存的是完整内容，没有项目 id，按其他项目处理
+fn score(recent: &[String], id: &str, summary: &str) -> f64 {
+    let current = shingles(summary);
+    recent
+        .iter()
+        .map(|value| {
+            serde_json::from_str(value).unwrap_or_else(|_| Recent {
+                id: String::new(),
+                summary: value.clone(),
+            })
+        })
+        .filter(|recent| recent.id != id)
+        .map(|recent| jaccard(&current, &shingles(&recent.summary)))
+        .fold(0.0, f64::max)
+}
+
+pub async fn remember(
+    conn: &mut redis::aio::Connection,
+    config: &SimilarityConfig,
+    id: &str,
+    summary: &str,
+) -> Result<()> {
+    let recent = Recent {
+        id: id.to_string(),
+        summary: summary.to_string(),
+    };
+    conn.lpush::<_, _, ()>(&config.key, serde_json::to_string(&recent)?).await?;
+    conn.ltrim::<_, ()>(&config.key, 0, config.window as isize - 1).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    #[test]
+    fn test_score_skips_same_repo() {
+        let summary = "servo 是一个用 Rust 编写的浏览器引擎，支持并行渲染。";
+        let recent = vec![
+            serde_json::json!({"id": "servo/servo", "summary": summary}).to_string(),
+            serde_json::json!({"id": "a/b", "summary": "一个命令行工具，用于管理 dotfiles。"}).to_string(),
+        ];
+        assert!(score(&recent, "servo/servo", summary) < 0.5);
+        assert!(score(&recent, "other/servo", summary) > 0.99);
+        assert!(score(&[summary.to_string()], "servo/servo", summary) > 0.99);
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use redis::AsyncCommands;
+use time::OffsetDateTime;
+use crate::config::Config;
+use crate::repo::PostRecord;
+
+// 统计去重 key 中保存的推送记录，只包含仍在推送周期内的记录
+pub async fn run(config: &Config, conn: &mut redis::aio::Connection) -> Result<()> {
+    let mut keys: Vec<String> = vec![];
+    for platform in config.platforms() {
+        let mut iter = conn.scan_match::<_, String>(format!("{}:*", platform.name())).await?;
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+    }
+
+    let mut per_day: BTreeMap<String, usize> = BTreeMap::new();
+    let mut per_language: BTreeMap<String, usize> = BTreeMap::new();
+    let mut per_platform: BTreeMap<String, usize> = BTreeMap::new();
+    for key in keys.iter() {
+        let value: Option<String> = conn.get(key).await?;
+        let Some(value) = value else {
+            continue;
+        };
+        // 旧版本只保存了时间戳
+        let record = serde_json::from_str::<PostRecord>(&value).unwrap_or_else(|_| PostRecord {
+            ts: value.parse().unwrap_or_default(),
+            platform: key.split(':').next().unwrap_or_default().to_string(),
+            language: String::new(),
+            message_id: None,
+            stars: None,
+        });
+        let day = OffsetDateTime::from_unix_timestamp(record.ts as i64)?.date().to_string();
+        let language = if record.language.is_empty() {
+            "unknown".to_string()
+        } else {
+            record.language
+        };
+        *per_day.entry(day).or_default() += 1;
+        *per_language.entry(language).or_default() += 1;
+        *per_platform.entry(record.platform).or_default() += 1;
+    }
+
+    println!("total posts: {}", keys.len());
+    for (title, counts) in [
+        ("per day", per_day),
+        ("per language", per_language),
+        ("per platform", per_platform),
+    ] {
+        println!("\n{}:", title);
+        for (name, count) in counts {
+            println!("  {:<20} {}", name, count);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use anyhow::{Context, Result};
+use log::info;
+use serde::Deserialize;
+use crate::repo::now_ts;
+
+// 推送去重记录的存储方式
+#[derive(Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StoreConfig {
+    // 保存在 Redis 中，记录消息 id 等完整信息
+    #[default]
+    Redis,
+    // 追加写入本地文件，只记录去重 key 和过期时间；没有开启依赖 Redis 的功能时可以不配置 [redis]
+    File { path: String },
+}
+
+// 每行为 key<TAB>过期时间，启动时读入内存，推送后追加一行
+pub struct FileStore {
+    path: String,
+    entries: HashMap<String, u64>,
+    // 文件中的行数，过期或重复的行超过一半时压缩
+    lines: usize,
+}
+
+impl FileStore {
+    pub fn open(path: &str) -> Result<Self> {
+        let mut store = FileStore {
+            path: path.to_string(),
+            entries: HashMap::new(),
+            lines: 0,
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(e).context(format!("While opening {}", path)),
+        };
+        let now = now_ts();
+        for line in BufReader::new(file).lines() {
+            let line = line.context(format!("While reading {}", path))?;
+            store.lines += 1;
+            let Some((key, expires_at)) = line.split_once('\t') else {
+                continue;
+            };
+            match expires_at.parse::<u64>() {
+                Ok(expires_at) if expires_at > now => {
+                    store.entries.insert(key.to_string(), expires_at);
+                }
+                _ => {}
+            }
+        }
+        store.compact_if_needed()?;
+        Ok(store)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.get(key).is_some_and(|expires_at| *expires_at > now_ts())
+    }
+
+    pub fn insert(&mut self, key: &str, ttl: usize) -> Result<()> {
+        let expires_at = now_ts() + ttl as u64;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!("While opening {}", self.path))?;
+        writeln!(file, "{}\t{}", key, expires_at).context(format!("While writing {}", self.path))?;
+        self.entries.insert(key.to_string(), expires_at);
+        self.lines += 1;
+        self.compact_if_needed()
+    }
+
+    // 丢弃过期记录后写入临时文件再替换，避免中途退出时丢失数据
+    fn compact_if_needed(&mut self) -> Result<()> {
+        let now = now_ts();
+        self.entries.retain(|_, expires_at| *expires_at > now);
+        if self.lines <= self.entries.len() * 2 + 100 {
+            return Ok(());
+        }
+        let tmp = format!("{}.tmp", self.path);
+        let content: String = self
+            .entries
+            .iter()
+            .map(|(key, expires_at)| format!("{}\t{}\n", key, expires_at))
+            .collect();
+        fs::write(&tmp, content).context(format!("While writing {}", tmp))?;
+        fs::rename(&tmp, &self.path).context(format!("While replacing {}", self.path))?;
+        info!("compacted {} from {} to {} lines", self.path, self.lines, self.entries.len());
+        self.lines = self.entries.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileStore;
+
+    #[test]
+    fn test_file_store_reload() {
+        let path = std::env::temp_dir().join(format!("github-trending-store-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let mut store = FileStore::open(path).unwrap();
+        store.insert("zsxq:servo/servo", 3600).unwrap();
+        assert!(store.contains("zsxq:servo/servo"));
+        std::fs::write(path, format!("{}expired/repo\t1\n", std::fs::read_to_string(path).unwrap())).unwrap();
+
+        let store = FileStore::open(path).unwrap();
+        assert!(store.contains("zsxq:servo/servo"));
+        assert!(!store.contains("expired/repo"));
+        assert!(!store.contains("bluesky:servo/servo"));
+        std::fs::remove_file(path).unwrap();
+    }
+}